@@ -0,0 +1,117 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+// Reuse the game from the `tui` example, which is written independently of any UI.
+#[path = "../tui/game.rs"]
+#[allow(dead_code)]
+mod game;
+use game::{Player, TicTacToe};
+
+use std::{env, process::ExitCode};
+
+use posturn::{
+   mcts::{Budget, Mcts},
+   rng::Rng,
+   search::MinimaxAgent,
+   simulate::Simulation,
+   Host, Session,
+};
+
+/// The usage message printed when the command line can't be parsed.
+const USAGE : &str = "\
+Usage: cargo run --release --example simulate -- [OPTIONS]
+
+Plays many games of Tic Tac Toe between bots, then prints aggregate statistics.
+
+Options:
+  --games <N>        Number of games to play [default: 1000]
+  --threads <N>      Number of threads to play games on [default: 1]
+  --seed <N>         Seed for all random choices [default: 0]
+  --x <AGENT>        Agent playing X: random, minimax or mcts [default: random]
+  --o <AGENT>        Agent playing O: random, minimax or mcts [default: minimax]
+  --format <FORMAT>  Output format: json or csv [default: json]";
+
+/// Command-line options for a simulation.
+struct Options {
+   games : usize,
+   threads : usize,
+   seed : u64,
+   x : String,
+   o : String,
+   format : String,
+}
+
+impl Options {
+   /// Parses options from the command line, or returns [`None`] if they don't make sense.
+   fn parse() -> Option<Self> {
+      let mut options = Self {
+         games: 1000,
+         threads: 1,
+         seed: 0,
+         x: "random".to_owned(),
+         o: "minimax".to_owned(),
+         format: "json".to_owned(),
+      };
+
+      let mut args = env::args().skip(1);
+      while let Some(flag) = args.next() {
+         let value = args.next()?;
+         match flag.as_str() {
+            "--games" => options.games = value.parse().ok()?,
+            "--threads" => options.threads = value.parse().ok()?,
+            "--seed" => options.seed = value.parse().ok()?,
+            "--x" => options.x = value,
+            "--o" => options.o = value,
+            "--format" => options.format = value,
+            _ => return None,
+         }
+      }
+
+      let agents = ["random", "minimax", "mcts"];
+      let is_valid = agents.contains(&options.x.as_str()) && agents.contains(&options.o.as_str()) &&
+         ["json", "csv"].contains(&options.format.as_str());
+      is_valid.then_some(options)
+   }
+}
+
+/// Binds the agent named `agent` to the seat of `player`. Random players are left unbound, so that the simulation
+/// picks a random legal move for them.
+fn bind(session : &mut Session<TicTacToe>, player : Player, agent : &str, seed : u64) {
+   match agent {
+      "minimax" => {
+         let board_area = (TicTacToe::BOARD_SIZE * TicTacToe::BOARD_SIZE) as usize;
+         session.bind(player.into(), MinimaxAgent::new(board_area, TicTacToe::evaluate));
+      },
+      "mcts" => session.bind(player.into(), Mcts::new(Budget::Iterations(200), seed)),
+      _ => (),
+   }
+}
+
+fn main() -> ExitCode {
+   let Some(options) = Options::parse() else {
+      eprintln!("{USAGE}");
+      return ExitCode::FAILURE;
+   };
+
+   let mut simulation = Simulation::new(options.games, |seed| {
+      // Give each seat its own seed, so that two bots of the same kind don't make the exact same random choices.
+      let mut seeds = Rng::new(seed);
+      let mut session = Session::new(Host::new(TicTacToe::default())).unwrap();
+      bind(&mut session, Player::X, &options.x, seeds.next_u64());
+      bind(&mut session, Player::O, &options.o, seeds.next_u64());
+      session
+   });
+   simulation.set_threads(options.threads);
+   simulation.set_seed(options.seed);
+
+   let report = simulation.run();
+   if options.format == "csv" {
+      print!("{}", report.to_csv());
+   }
+   else {
+      println!("{}", report.to_json());
+   }
+
+   ExitCode::SUCCESS
+}
@@ -2,8 +2,6 @@
 //
 // SPDX-License-Identifier: MIT
 
-use std::u16;
-
 /// Represents a player or player piece in a game of [`TicTacToe`].
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum Player {
@@ -24,12 +22,11 @@ impl std::fmt::Display for Player {
    }
 }
 
-impl Player {
-   /// Returns the next player in the turn order.
-   fn next(&self) -> Self {
-      match self {
-         Self::X => Self::O,
-         Self::O => Self::X,
+impl From<Player> for posturn::Seat {
+   fn from(player : Player) -> Self {
+      match player {
+         Player::X => Self(0),
+         Player::O => Self(1),
       }
    }
 }
@@ -38,7 +35,7 @@ impl Player {
 pub type Board = [Option<Player>; (TicTacToe::BOARD_SIZE * TicTacToe::BOARD_SIZE) as usize];
 
 /// Represents a position on a [`TicTacToe`] game board. Guaranteed to be valid.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Pos(u16, u16);
 
 impl Pos {
@@ -142,7 +139,7 @@ impl Iterator for TilesInLine {
          Line::Col(col) => (col, offset).try_into().ok(),
          Line::Diagonal(flip_row) => {
             let pos = (offset, offset).try_into().ok();
-            if flip_row { pos.and_then(|pos : Pos| Some(pos.flip_row())) } else { pos }
+            if flip_row { pos.map(|pos : Pos| pos.flip_row()) } else { pos }
          },
       }
    }
@@ -158,19 +155,43 @@ pub enum Outcome {
    Win(Player, Line),
 }
 
-#[derive(Debug, Default)]
+impl posturn::Ranked for Outcome {
+   fn standings(&self) -> posturn::Standings {
+      let seats = [Player::X, Player::O].map(posturn::Seat::from);
+      match self {
+         Self::CatsGame => posturn::Standings::draw(seats),
+         Self::Win(winner, _) => {
+            let winner = posturn::Seat::from(*winner);
+            posturn::Standings::win(winner, seats.into_iter().filter(|seat| *seat != winner))
+         },
+      }
+   }
+}
+
+#[derive(Clone, Debug)]
 pub struct TicTacToe {
-   current_player : Player,
+   turn_order : posturn::TurnOrder<Player>,
    board : Board,
    outcome : Option<Outcome>,
 }
 
+impl Default for TicTacToe {
+   fn default() -> Self {
+      Self {
+         turn_order: posturn::TurnOrder::new([Player::X, Player::O]),
+         board: Default::default(),
+         outcome: None,
+      }
+   }
+}
+
 impl TicTacToe {
    /// The width and height, in tiles, of the (square) board.
    pub const BOARD_SIZE : u16 = 3;
 
    /// Attempts to claim a tile on the board at the specified [`Pos`] for the current player.
    fn take_turn(&mut self, pos : Pos) -> Result<(), InvalidMove> {
+      let player = self.current_player();
       let index = pos.index();
       let tile = &mut self.board[index];
 
@@ -180,10 +201,7 @@ impl TicTacToe {
       }
 
       // Claim the tile.
-      *tile = Some(self.current_player);
-
-      // Now it's the next player's turn.
-      self.current_player = self.current_player.next();
+      *tile = Some(player);
 
       Ok(())
    }
@@ -244,7 +262,29 @@ impl TicTacToe {
 
    /// Returns the player whose turn it is.
    pub fn current_player(&self) -> Player {
-      self.current_player
+      self.turn_order.current().copied().unwrap_or_default()
+   }
+
+   /// Iterates over every [`Pos`] on the game board that has not been claimed yet, in row-major order.
+   pub fn open_tiles(&self) -> impl Iterator<Item = Pos> + '_ {
+      (0..Self::BOARD_SIZE)
+         .flat_map(|row| (0..Self::BOARD_SIZE).map(move |col| Pos(col, row)))
+         .filter(|pos| self.tile(*pos).is_none())
+   }
+
+   /// Scores a game in progress from the point of view of `seat`, for use with [`posturn::search::minimax`]. Only
+   /// finished games are scored, and quicker wins (or slower losses) score better.
+   pub fn evaluate(session : &posturn::Session<Self>, seat : posturn::Seat) -> i32 {
+      let Some(outcome) = session.outcome() else {
+         return 0;
+      };
+
+      let open_tiles = session.host().with_game(|game| game.open_tiles().count()) as i32;
+      match outcome {
+         Outcome::CatsGame => 0,
+         Outcome::Win(player, _) if posturn::Seat::from(*player) == seat => 10 + open_tiles,
+         Outcome::Win(..) => -10 - open_tiles,
+      }
    }
 
    /// Borrows the tile at the specified [`Pos`] on the game board.
@@ -264,32 +304,52 @@ impl posturn::Play for TicTacToe {
    type Event = Result<Player, InvalidMove>;
    type Outcome = Outcome;
 
-   fn play(ctx : posturn::Context<Self>) -> impl std::future::Future<Output = Self::Outcome> {
-      async move {
-         let mut last_turn_result = Ok(());
-
+   async fn play(ctx : posturn::Context<Self>) -> Self::Outcome {
+      // Pass the turn and wait for the new player to supply a position to claim. A copy of the game made during a turn
+      // (e.g. one being searched) picks up on the same player's turn.
+      while let Some((_, mut pos)) = ctx.next_turn().await {
+         // Attempt to place a piece for the current player, asking again until the move is valid.
          loop {
-            let next_event = last_turn_result.map(|_| {
-               // Ask the player for an input.
-               let current_player = ctx.host.borrow_game().current_player;
-               current_player
-            });
-
-            // Wait for the player to supply a position to claim.
-            let pos = ctx.yield_event(next_event).await.into();
-
-            // Attempt to place a piece for the current player.
-            last_turn_result = ctx.host.borrow_game_mut().take_turn(pos);
-
-            if last_turn_result.is_err() {
-               continue;
-            }
-            else if let Some(outcome) = ctx.host.with_game(|game| game.check_outcome()) {
-               // Game over!
-               ctx.host.borrow_game_mut().outcome = Some(outcome);
-               return outcome;
+            let result = ctx.host.borrow_game_mut().take_turn(pos);
+            match result {
+               Ok(()) => break,
+               Err(invalid_move) => pos = ctx.yield_event(Err(invalid_move)).await,
             }
          }
+
+         if let Some(outcome) = ctx.host.with_game(|game| game.check_outcome()) {
+            // Game over!
+            ctx.host.borrow_game_mut().outcome = Some(outcome);
+            return outcome;
+         }
       }
+
+      unreachable!("both players take turns until the game is over")
+   }
+}
+
+impl posturn::TakeTurns for TicTacToe {
+   type Player = Player;
+
+   fn turn_order(&mut self) -> &mut posturn::TurnOrder<Player> {
+      &mut self.turn_order
+   }
+
+   fn turn_changed(&self, player : &Player) -> Self::Event {
+      Ok(*player)
+   }
+}
+
+impl posturn::LegalMoves for TicTacToe {
+   fn legal_inputs(&self, _pending : &Self::Event) -> impl Iterator<Item = Pos> {
+      // Any tile that hasn't been claimed yet can be claimed, as long as the game isn't over.
+      self.open_tiles().filter(|_| self.outcome.is_none())
    }
-}
\ No newline at end of file
+}
+
+impl posturn::Seated for TicTacToe {
+   fn seats_to_answer(&self, _prompt : &Self::Event) -> posturn::Seats {
+      // Only the current player may claim a tile, even if their last attempt was invalid.
+      posturn::Seat::from(self.current_player()).into()
+   }
+}
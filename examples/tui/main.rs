@@ -3,16 +3,16 @@
 // SPDX-License-Identifier: MIT
 
 mod game;
-use game::TicTacToe;
+use game::{Player, TicTacToe};
 
 mod view;
 use view::View;
 
-use futures::pin_mut;
 use std::io::{self, stdout};
 
+use posturn::search::MinimaxAgent;
+
 use crossterm::{queue, terminal};
-use posturn::genawaiter::Coroutine;
 
 fn main() -> io::Result<()> {
    let mut out = stdout();
@@ -23,23 +23,25 @@ fn main() -> io::Result<()> {
       let mut view = View::new(terminal::size()?);
       
       let host = posturn::Host::new(TicTacToe::default());
-      let co = host.play().unwrap();
-      pin_mut!(co);
-
-      let mut pos = Default::default();
-      let mut last_outcome = None;
+      let mut session = posturn::Session::new(host.clone()).unwrap();
+      session.validate_inputs();
 
-      while last_outcome.is_none() {
-         // NOTE: We need to call this once with a default argument to start the game, hence being at the top of the loop.
-         last_outcome = match co.as_mut().resume_with(pos) {
-            genawaiter::GeneratorState::Yielded(_) => None,
-            genawaiter::GeneratorState::Complete(outcome) => Some(outcome),
-         };
+      // The AI opponent searches every possible game to the end, so it never loses.
+      let board_area = (TicTacToe::BOARD_SIZE * TicTacToe::BOARD_SIZE) as usize;
+      session.bind(Player::O.into(), MinimaxAgent::new(board_area, TicTacToe::evaluate));
 
-         match host.with_game(|game| view.wait_for_input(&mut out, &game))? {
+      loop {
+         let playable = session.legal_inputs();
+         match host.with_game(|game| view.wait_for_input(&mut out, &game, &playable))? {
             view::Event::TakeTurn(col, row) => {
-               // Place a piece and update the turn-based game.
-               pos = (col, row).try_into().expect("Invalid position");
+               // Place a piece for the current player and update the turn-based game.
+               let pos = (col, row).try_into().expect("Invalid position");
+               let seat = host.borrow_game().current_player().into();
+               match session.resume_as(seat, pos) {
+                  // Claimed tiles aren't playable, so there's nothing to do.
+                  Err(posturn::session::SessionError::IllegalInput) => (),
+                  result => { result.expect("Failed to take turn"); },
+               }
             },
             view::Event::NewGame => continue 'new_game,
             view::Event::Quit => break 'new_game,
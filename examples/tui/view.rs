@@ -58,6 +58,9 @@ impl View {
    /// Used to pad the characters written in the prompt area.
    const PROMPT_MAX_WIDTH : usize = 20;
 
+   /// The marker drawn on tiles that the current player can claim.
+   const PLAYABLE_MARKER : &'static str = "·";
+
    /// The text displaying controls to the player.
    const CONTROLS_PROMPT : &'static str = "\
 ENTER : Claim tile
@@ -78,13 +81,14 @@ ENTER : Claim tile
       }
    }
 
-   /// Poll for input until the player does something that needs to update the state of the turn-based game.
-   pub fn wait_for_input<W>(&mut self, out : &mut W, game : &TicTacToe) -> io::Result<Event> where
+   /// Poll for input until the player does something that needs to update the state of the turn-based game. Tiles at
+   /// each of the `playable` positions are highlighted.
+   pub fn wait_for_input<W>(&mut self, out : &mut W, game : &TicTacToe, playable : &[Pos]) -> io::Result<Event> where
       W : io::Write,
    {
       loop {
          // Update to match the game board.
-         self.redraw(out, game)?;
+         self.redraw(out, game, playable)?;
          out.flush()?;
    
          let event = event::read()?;
@@ -94,7 +98,7 @@ ENTER : Claim tile
                self.terminal_size = (width, height);
             },
             event::Event::Key(event::KeyEvent { code, kind: event::KeyEventKind::Press, .. }) => {
-               let event_to_process = if let Some(_) = game.outcome() {
+               let event_to_process = if game.outcome().is_some() {
                   self.handle_game_over_key_press(code)
                }
                else {
@@ -153,7 +157,7 @@ ENTER : Claim tile
       }
    }
 
-   pub fn redraw<W>(&mut self, out : &mut W, game : &TicTacToe) -> io::Result<()>
+   pub fn redraw<W>(&mut self, out : &mut W, game : &TicTacToe, playable : &[Pos]) -> io::Result<()>
       where W : io::Write,
    {
       if self.was_resized {
@@ -201,7 +205,17 @@ ENTER : Claim tile
       for row in 0..TicTacToe::BOARD_SIZE {
          for col in 0..TicTacToe::BOARD_SIZE {
             let pos : Pos = (col, row).try_into().unwrap();
-            if let Some(player) = game.tile(pos) {
+            if playable.contains(&pos) {
+               // Highlight tiles that the current player can claim.
+               let tile_pos = Self::calc_tile_pos((col, row));
+               queue!(
+                  out,
+                  cursor::MoveTo(tile_pos.0, tile_pos.1),
+                  style::PrintStyledContent(Self::PLAYABLE_MARKER.green()),
+                  style::SetBackgroundColor(style::Color::DarkBlue),
+               )?;
+            }
+            else if let Some(player) = game.tile(pos) {
                let tile_pos = Self::calc_tile_pos((col, row));
                let piece = format!("{}", player);
                
@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::Play;
+
+/// A computer-controlled player. Binding an agent to a [`Seat`](crate::Seat) via
+/// [`Session::bind`](crate::Session::bind) makes the session answer every prompt meant for that seat automatically,
+/// so that a frontend only ever sees the prompts meant for human players.
+///
+/// Any `FnMut(&Game, &Game::Event) -> Game::Input` closure can be used as an agent.
+pub trait Agent<Game> where
+   Game : Play,
+{
+   /// Chooses an [`Input`](Play::Input) in response to `prompt`, given a read-only view of the game state.
+   fn choose(&mut self, game : &Game, prompt : &Game::Event) -> Game::Input;
+}
+
+impl<Game, F> Agent<Game> for F where
+   Game : Play,
+   F : FnMut(&Game, &Game::Event) -> Game::Input,
+{
+   fn choose(&mut self, game : &Game, prompt : &Game::Event) -> Game::Input {
+      self(game, prompt)
+   }
+}
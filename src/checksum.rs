@@ -0,0 +1,105 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::hash::{Hash, Hasher};
+
+/// Optional trait for games that can reduce their state to a checksum. Hosts on both ends of a replicated game can
+/// compare checksums after each [`Event`](crate::Play::Event) to detect when they have fallen out of sync. See
+/// [`Host::enable_checksums`](crate::Host::enable_checksums).
+///
+/// The easiest way to implement this for a game implementing [`Hash`] is via [`StateHasher::hash_of`].
+pub trait StateHash {
+   /// Computes a checksum of the game state. This **must** produce the same value for equivalent states, even across
+   /// different processes or machines.
+   fn state_hash(&self) -> u64;
+}
+
+/// A [`Hasher`] implementing 64-bit
+/// [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function). Unlike
+/// [`DefaultHasher`](std::collections::hash_map::DefaultHasher), its output is stable across processes and Rust
+/// versions, which makes it suitable for comparing game states between machines.
+#[derive(Clone, Copy, Debug)]
+pub struct StateHasher(u64);
+
+impl StateHasher {
+   const OFFSET_BASIS : u64 = 0xcbf29ce484222325;
+   const PRIME : u64 = 0x00000100000001b3;
+
+   /// Hashes any value implementing [`Hash`] with a fresh [`StateHasher`].
+   pub fn hash_of<T>(value : &T) -> u64 where
+      T : Hash + ?Sized,
+   {
+      let mut hasher = Self::default();
+      value.hash(&mut hasher);
+      hasher.finish()
+   }
+}
+
+impl Default for StateHasher {
+   fn default() -> Self {
+      Self(Self::OFFSET_BASIS)
+   }
+}
+
+impl Hasher for StateHasher {
+   fn finish(&self) -> u64 {
+      self.0
+   }
+
+   fn write(&mut self, bytes : &[u8]) {
+      for byte in bytes {
+         self.0 ^= *byte as u64;
+         self.0 = self.0.wrapping_mul(Self::PRIME);
+      }
+   }
+
+   // NOTE: Integers are hashed in a fixed byte order so that checksums agree between platforms.
+   fn write_u16(&mut self, i : u16) { self.write(&i.to_le_bytes()) }
+   fn write_u32(&mut self, i : u32) { self.write(&i.to_le_bytes()) }
+   fn write_u64(&mut self, i : u64) { self.write(&i.to_le_bytes()) }
+   fn write_u128(&mut self, i : u128) { self.write(&i.to_le_bytes()) }
+   fn write_usize(&mut self, i : usize) { self.write_u64(i as u64) }
+   fn write_i16(&mut self, i : i16) { self.write_u16(i as u16) }
+   fn write_i32(&mut self, i : i32) { self.write_u32(i as u32) }
+   fn write_i64(&mut self, i : i64) { self.write_u64(i as u64) }
+   fn write_i128(&mut self, i : i128) { self.write_u128(i as u128) }
+   fn write_isize(&mut self, i : isize) { self.write_u64(i as u64) }
+}
+
+/// An [`Event`](crate::Play::Event) stamped with the checksum of the game state immediately after it was processed by
+/// the sending [`Host`](crate::Host). Produced by [`Host::checked`](crate::Host::checked), and consumed on the other
+/// end by [`Host::process_checked`](crate::Host::process_checked).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Checked<Event> {
+   /// The zero-based index of the event among all events processed by the sending host.
+   pub index : u64,
+
+   /// The checksum of the sending host's game state after processing the event.
+   pub checksum : u64,
+
+   /// The event itself.
+   pub event : Event,
+}
+
+/// Returned when the game state of a receiving [`Host`](crate::Host) no longer matches the state of the sender.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Desync {
+   /// The index of the first event after which the two game states diverged.
+   pub index : u64,
+
+   /// The checksum computed by the sender.
+   pub expected : u64,
+
+   /// The checksum computed locally.
+   pub actual : u64,
+}
+
+impl std::fmt::Display for Desync {
+   fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      write!(f, "game state desynced at event {} (expected checksum {:#018x}, got {:#018x})",
+         self.index, self.expected, self.actual)
+   }
+}
+
+impl std::error::Error for Desync { }
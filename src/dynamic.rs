@@ -0,0 +1,199 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{any::Any, collections::BTreeMap};
+
+use genawaiter::GeneratorState;
+
+use crate::{net::{Wire, WireError}, session::SessionError, Play, Seat, Seated, Seats, Session};
+
+/// Returned when a [`DynSession`] or [`Registry`] can't do what was asked of it.
+#[derive(Debug, Eq, PartialEq)]
+pub enum DynError {
+   /// The underlying [`Session`] rejected the input.
+   Session(SessionError),
+
+   /// An input or config could not be decoded.
+   Wire(WireError),
+
+   /// No game is registered under this name.
+   UnknownGame(String),
+}
+
+impl From<SessionError> for DynError {
+   fn from(err : SessionError) -> Self {
+      Self::Session(err)
+   }
+}
+
+impl From<WireError> for DynError {
+   fn from(err : WireError) -> Self {
+      Self::Wire(err)
+   }
+}
+
+/// The state of a [`DynSession`]: either the pending [`Event`](Play::Event) or the [`Outcome`](Play::Outcome), encoded
+/// via [`Wire`].
+pub type DynState = GeneratorState<Vec<u8>, Vec<u8>>;
+
+/// An object-safe, type-erased [`Session`], so that many different kinds of games can be hosted side by side, e.g. as
+/// `Box<dyn DynSession>`. Every [`Event`](Play::Event), [`Input`](Play::Input) and [`Outcome`](Play::Outcome) is
+/// passed in and out in its [`Wire`] encoding, which can be forwarded to clients as is.
+///
+/// This is implemented for every [`Session`] whose game is [`Seated`], and whose events, inputs and outcomes implement
+/// [`Wire`]. The original session can be recovered via [`downcast_ref`](#method.downcast_ref).
+pub trait DynSession {
+   /// Returns the name of the game's type, for diagnostics.
+   fn game_type(&self) -> &'static str;
+
+   /// Returns the encoded pending event, or the encoded outcome if the game is over.
+   fn state(&self) -> DynState;
+
+   /// Returns `true` if the game is over.
+   fn is_complete(&self) -> bool;
+
+   /// Returns the seats allowed to answer the pending prompt. See [`Session::seats_to_answer`].
+   fn seats_to_answer(&self) -> Seats;
+
+   /// Decodes `input` and resumes the game with it, returning the new state. See [`Session::resume`].
+   ///
+   /// Since type-erased sessions are meant to be driven by remote players, prompts that must be answered by
+   /// particular seats are always rejected with [`SessionError::SeatRequired`], as if seats were required via
+   /// [`Session::require_seats`]. These prompts must be answered via [`resume_as`](Self::resume_as) instead.
+   fn resume(&mut self, input : &[u8]) -> Result<DynState, DynError>;
+
+   /// Decodes `input` and resumes the game with it on behalf of `seat`, returning the new state. See
+   /// [`Session::resume_as`].
+   fn resume_as(&mut self, seat : Seat, input : &[u8]) -> Result<DynState, DynError>;
+
+   /// Returns the session as [`Any`], for downcasting.
+   fn as_any(&self) -> &dyn Any;
+
+   /// Returns the session as [`Any`], for downcasting.
+   fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl dyn DynSession {
+   /// Returns the underlying [`Session`], if it is playing a `Game`.
+   pub fn downcast_ref<Game>(&self) -> Option<&Session<Game>> where
+      Game : Play + 'static,
+   {
+      self.as_any().downcast_ref()
+   }
+
+   /// Returns the underlying [`Session`], if it is playing a `Game`.
+   pub fn downcast_mut<Game>(&mut self) -> Option<&mut Session<Game>> where
+      Game : Play + 'static,
+   {
+      self.as_any_mut().downcast_mut()
+   }
+}
+
+impl<Game> DynSession for Session<Game> where
+   Game : Seated + 'static,
+   Game::Input : Wire,
+   Game::Event : Wire,
+   Game::Outcome : Wire,
+{
+   fn game_type(&self) -> &'static str {
+      std::any::type_name::<Game>()
+   }
+
+   fn state(&self) -> DynState {
+      encode_state(Session::state(self))
+   }
+
+   fn is_complete(&self) -> bool {
+      Session::is_complete(self)
+   }
+
+   fn seats_to_answer(&self) -> Seats {
+      Session::seats_to_answer(self)
+   }
+
+   fn resume(&mut self, input : &[u8]) -> Result<DynState, DynError> {
+      let input = Game::Input::from_bytes(input)?;
+      if !Session::seats_to_answer(self).is_empty() {
+         return Err(SessionError::SeatRequired.into());
+      }
+
+      Ok(encode_state(Session::resume(self, input)?))
+   }
+
+   fn resume_as(&mut self, seat : Seat, input : &[u8]) -> Result<DynState, DynError> {
+      let input = Game::Input::from_bytes(input)?;
+      Ok(encode_state(Session::resume_as(self, seat, input)?))
+   }
+
+   fn as_any(&self) -> &dyn Any {
+      self
+   }
+
+   fn as_any_mut(&mut self) -> &mut dyn Any {
+      self
+   }
+}
+
+/// Encodes the event or outcome in `state` via [`Wire`].
+fn encode_state<Event, Outcome>(state : GeneratorState<&Event, &Outcome>) -> DynState where
+   Event : Wire,
+   Outcome : Wire,
+{
+   match state {
+      GeneratorState::Yielded(event) => GeneratorState::Yielded(event.to_bytes()),
+      GeneratorState::Complete(outcome) => GeneratorState::Complete(outcome.to_bytes()),
+   }
+}
+
+/// Creates a type-erased session from an encoded config.
+type CreateFn = Box<dyn Fn(&[u8]) -> Result<Box<dyn DynSession>, DynError>>;
+
+/// A collection of games that can be started by name, e.g. by a server hosting many kinds of games. Each game is
+/// started from a config, which is passed in its [`Wire`] encoding so that it can come straight from a client.
+#[derive(Default)]
+pub struct Registry {
+   games : BTreeMap<String, CreateFn>,
+}
+
+impl Registry {
+   /// Creates a new, empty [`Registry`].
+   pub fn new() -> Self {
+      Self::default()
+   }
+
+   /// Registers a game under `name`, replacing any game that was already registered under it. Sessions are created by
+   /// `create`, given a decoded `Config`, e.g. to bind [`Agent`s](crate::Agent) before the session is handed out.
+   pub fn register<Game, Config>(
+      &mut self,
+      name : impl Into<String>,
+      create : impl Fn(Config) -> Session<Game> + 'static,
+   ) where
+      Game : Seated + 'static,
+      Game::Input : Wire,
+      Game::Event : Wire,
+      Game::Outcome : Wire,
+      Config : Wire,
+   {
+      let create = move |config : &[u8]| -> Result<Box<dyn DynSession>, DynError> {
+         Ok(Box::new(create(Config::from_bytes(config)?)))
+      };
+      self.games.insert(name.into(), Box::new(create));
+   }
+
+   /// Returns `true` if a game is registered under `name`.
+   pub fn contains(&self, name : &str) -> bool {
+      self.games.contains_key(name)
+   }
+
+   /// Returns the names of every registered game, in alphabetical order.
+   pub fn names(&self) -> impl Iterator<Item = &str> {
+      self.games.keys().map(String::as_str)
+   }
+
+   /// Starts a new session of the game registered under `name`, decoding its config from `config`.
+   pub fn create(&self, name : &str, config : &[u8]) -> Result<Box<dyn DynSession>, DynError> {
+      let create = self.games.get(name).ok_or_else(|| DynError::UnknownGame(name.into()))?;
+      create(config)
+   }
+}
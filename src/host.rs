@@ -2,16 +2,27 @@
 //
 // SPDX-License-Identifier: MIT
 
-use std::{cell::{Ref, RefCell, RefMut}, rc::Rc};
+use std::{cell::{Cell, Ref, RefCell, RefMut}, pin::Pin, rc::Rc};
 
-use genawaiter::{rc::{Co, Gen}, Coroutine};
+use genawaiter::{rc::{Co, Gen}, Coroutine, GeneratorState};
 
-use crate::{Context, Play};
+use crate::{
+   checksum::{Checked, Desync, StateHash},
+   middleware::Intercept,
+   trigger::TriggerOverflow,
+   Context, Middleware, Play, Resume, Seats, Triggers, TryHandleEvent,
+};
 
 /// Shared helper structure that keeps track of whether a game has been started and also tracks game state.
 struct State<Game : Play> {
    is_in_progress : bool,
    game : Game,
+   middleware : Vec<Box<dyn Middleware<Game>>>,
+   triggers : Triggers<Game>,
+   event_count : u64,
+   hasher : Option<fn(&Game) -> u64>,
+   checksum : Option<u64>,
+   asking : Option<Seats>,
 }
 
 impl<Game> From<Game> for State<Game> where
@@ -21,6 +32,35 @@ impl<Game> From<Game> for State<Game> where
       Self {
          is_in_progress: false,
          game,
+         middleware: Vec::new(),
+         triggers: Triggers::default(),
+         event_count: 0,
+         hasher: None,
+         checksum: None,
+         asking: None,
+      }
+   }
+}
+
+impl<Game> State<Game> where
+   Game : Play,
+{
+   /// Runs an [`Event`](Play::Event) through each [`Middleware`] layer in order, stopping early if any layer drops it.
+   fn intercept(&mut self, event : &mut <Game as Play>::Event) -> Intercept {
+      for layer in self.middleware.iter_mut() {
+         if layer.intercept(&self.game, event) == Intercept::Drop {
+            return Intercept::Drop;
+         }
+      }
+
+      Intercept::Continue
+   }
+
+   /// Bookkeeping performed after the game has handled an [`Event`](Play::Event).
+   fn handled(&mut self) {
+      self.event_count += 1;
+      if let Some(hasher) = self.hasher {
+         self.checksum = Some(hasher(&self.game));
       }
    }
 }
@@ -59,17 +99,31 @@ impl<Game> Host<Game> where
          Return = <Game as Play>::Outcome>,
       PlayError>
    {
-      if let Ok(state) = self.state.try_borrow_mut() {
+      self.start().map(Inputs)
+   }
+
+   /// Starts a new game, returning the underlying coroutine, which is resumed with [`Resume`] values. This is shared by
+   /// [`play`](Self::play) and [`Session`](crate::Session).
+   pub(crate) fn start(&self) -> Result<
+      impl Coroutine<
+         Yield = <Game as Play>::Event,
+         Resume = Resume<<Game as Play>::Input>,
+         Return = <Game as Play>::Outcome> + Unpin,
+      PlayError>
+   {
+      if let Ok(mut state) = self.state.try_borrow_mut() {
          if state.is_in_progress {
             return Err(PlayError::AlreadyStarted);
          }
+
+         state.is_in_progress = true;
       }
       else {
          return Err(PlayError::InUse);
       }
 
-      let run = move |co : Co<Game::Event, Game::Input>| {
-         let ctx = Context { host: self.clone(), co };
+      let run = move |co : Co<Game::Event, Resume<Game::Input>>| {
+         let ctx = Context { host: self.clone(), co, has_passed_turn: Cell::new(false) };
          Game::play(ctx)
       };
 
@@ -142,16 +196,198 @@ impl<Game> Host<Game> where
       transact(borrowed)
    }
 
+   /// Appends a [`Middleware`] layer to the end of the pipeline that every [`Event`](Play::Event) passes through before
+   /// reaching [`handle_event`](Play::handle_event). Layers run in the order they were added, and apply equally to
+   /// events supplied via [`process_event`](Self::process_event) and events emitted via
+   /// [`Context::yield_event`].
+   /// 
+   /// # Safety
+   /// This function will panic if the game state is already being accessed by any of the `*_game` family of functions.
+   /// 
+   pub fn push_middleware<M>(&self, layer : M) where
+      M : Middleware<Game> + 'static,
+   {
+      self.state.borrow_mut().middleware.push(Box::new(layer));
+   }
+
+   /// Sets the maximum length of a chain of follow-up events queued by [`Play::trigger`]. Once a chain reaches this
+   /// depth, [`Triggers::push`] will refuse to queue any more events. Defaults to
+   /// [`Triggers::DEFAULT_MAX_DEPTH`].
+   /// 
+   /// # Safety
+   /// This function will panic if the game state is already being accessed by any of the `*_game` family of functions.
+   /// 
+   pub fn set_max_trigger_depth(&self, max_depth : usize) {
+      self.state.borrow_mut().triggers.set_max_depth(max_depth);
+   }
+
+   /// Takes every [`TriggerOverflow`] reported by [`Triggers::push`] since the last call, oldest first. A non-empty
+   /// result usually means that a chain of triggered abilities has formed a loop and was cut short.
+   /// 
+   /// # Safety
+   /// This function will panic if the game state is already being accessed by any of the `*_game` family of functions.
+   /// 
+   pub fn take_trigger_overflows(&self) -> Vec<TriggerOverflow> {
+      self.state.borrow_mut().triggers.take_overflows()
+   }
+
    /// Allows the game to update its state in response to an external [`Event`](Play::Event). This will internally call
    /// [`handle_event`](Play::handle_event), which is also called whenever an [`Event`](Play::Event) is generated by
    /// [`play`](Play::play).
    /// 
+   /// Before the game sees the event, it is passed through each [`Middleware`] layer in order. Layers may modify or
+   /// replace the event in place. If any layer returns [`Intercept::Drop`], the remaining layers are skipped, the game
+   /// state is left untouched, and this returns `false`. Otherwise, returns `true`. Callers that relay events (e.g. to
+   /// clients) should check this, since a dropped event must not be relayed.
+   /// 
    /// This is useful for unifying networked game logic where clients need to stay in sync with server state. The
    /// server can generate events and replicate them to the client, which can then process these same events to update
    /// its own game state.
    /// 
-   pub fn process_event(&self, mut event : &mut <Game as Play>::Event) {
-      self.with_game_mut(|mut game| game.handle_event(&mut event))
+   #[must_use = "the event may have been dropped by middleware"]
+   pub fn process_event(&self, event : &mut <Game as Play>::Event) -> bool {
+      let mut state = self.state.borrow_mut();
+      if state.intercept(event) == Intercept::Drop {
+         return false;
+      }
+
+      state.game.handle_event(event);
+      state.handled();
+      true
+   }
+
+   /// Passes an [`Event`](Play::Event) that has already been through [`Middleware`] (e.g. on another [`Host`]) straight
+   /// to [`handle_event`](Play::handle_event), so that it isn't intercepted twice.
+   pub(crate) fn apply_event(&self, event : &mut <Game as Play>::Event) {
+      let mut state = self.state.borrow_mut();
+      state.game.handle_event(event);
+      state.handled();
+   }
+
+   /// Gives the game a chance to queue follow-up events in response to an event emitted by [`play`](Play::play), where
+   /// `depth` is the depth of `event` in the current chain of triggers.
+   pub(crate) fn trigger(&self, event : &<Game as Play>::Event, depth : usize) {
+      let mut state = self.state.borrow_mut();
+      let State { game, triggers, .. } = &mut *state;
+      triggers.set_depth(depth);
+      game.trigger(event, triggers);
+   }
+
+   /// Removes the next follow-up event from the trigger queue, along with its depth in the chain of triggers.
+   pub(crate) fn pop_trigger(&self) -> Option<(usize, <Game as Play>::Event)> {
+      self.state.borrow_mut().triggers.pop()
+   }
+
+   /// Records that the coroutine is about to yield a prompt that must be answered by every one of `seats`.
+   pub(crate) fn set_asking(&self, seats : Option<Seats>) {
+      self.state.borrow_mut().asking = seats;
+   }
+
+   /// Takes the seats recorded by [`set_asking`](Self::set_asking), if any.
+   pub(crate) fn take_asking(&self) -> Option<Seats> {
+      self.state.borrow_mut().asking.take()
+   }
+
+   /// Fallible version of [`process_event`](Self::process_event) for games implementing [`TryHandleEvent`]. This is
+   /// intended for clients applying events replicated from a server, where an event that is illegal in the local game
+   /// state indicates that the two have fallen out of sync.
+   /// 
+   /// If the game rejects the event, returns the [`EventError`](TryHandleEvent::EventError) and leaves the game state
+   /// unchanged. Otherwise, returns `Ok(false)` if a [`Middleware`] layer dropped the event, or `Ok(true)` if the game
+   /// accepted it.
+   /// 
+   pub fn try_process_event(&self, event : &mut <Game as Play>::Event) -> Result<bool, Game::EventError> where
+      Game : TryHandleEvent,
+   {
+      let mut state = self.state.borrow_mut();
+      if state.intercept(event) == Intercept::Drop {
+         return Ok(false);
+      }
+
+      state.game.try_handle_event(event)?;
+      state.handled();
+      Ok(true)
+   }
+
+   /// Returns the number of [`Event`s](Play::Event) the game has handled so far, whether they were emitted by
+   /// [`play`](Play::play) or supplied via [`process_event`](Self::process_event). Events dropped by a [`Middleware`]
+   /// layer or rejected by [`TryHandleEvent`] are not counted.
+   pub fn event_count(&self) -> u64 {
+      self.state.borrow().event_count
+   }
+
+   /// Makes the [`Host`] compute a [`StateHash`] of the game state after every [`Event`](Play::Event) it handles. The
+   /// most recent checksum is available via [`checksum`](Self::checksum), and is attached to outgoing events by
+   /// [`checked`](Self::checked).
+   /// 
+   /// # Safety
+   /// This function will panic if the game state is already being accessed by any of the `*_game` family of functions.
+   /// 
+   pub fn enable_checksums(&self) where
+      Game : StateHash,
+   {
+      let mut state = self.state.borrow_mut();
+      state.hasher = Some(Game::state_hash);
+      state.checksum = Some(state.game.state_hash());
+   }
+
+   /// Returns the checksum of the game state computed after the most recently handled [`Event`](Play::Event), or
+   /// [`None`] if checksums have not been enabled via [`enable_checksums`](Self::enable_checksums).
+   pub fn checksum(&self) -> Option<u64> {
+      self.state.borrow().checksum
+   }
+
+   /// Recomputes the checksum from the current game state, so that it accounts for any changes made since the most
+   /// recently handled [`Event`](Play::Event). Returns [`None`] if checksums have not been enabled.
+   pub(crate) fn refresh_checksum(&self) -> Option<u64> {
+      let mut state = self.state.borrow_mut();
+      state.checksum = state.hasher.map(|hasher| hasher(&state.game));
+      state.checksum
+   }
+
+   /// Replaces the game state (if given) with a snapshot taken after `event_count` events were handled, and brings the
+   /// event count and checksum in line with it.
+   pub(crate) fn restore(&self, game : Option<Game>, event_count : u64) {
+      if let Some(game) = game {
+         self.state.borrow_mut().game = game;
+      }
+      self.state.borrow_mut().event_count = event_count;
+      self.refresh_checksum();
+   }
+
+   /// Stamps an [`Event`](Play::Event) that this [`Host`] has just handled (e.g. one that was just yielded by
+   /// [`play`](Self::play)) with its index and the resulting checksum, so that it can be replicated to other hosts and
+   /// verified with [`process_checked`](Self::process_checked). If checksums are not enabled, the checksum of the
+   /// current game state is computed on the spot.
+   pub fn checked(&self, event : <Game as Play>::Event) -> Checked<<Game as Play>::Event> where
+      Game : StateHash,
+   {
+      let state = self.state.borrow();
+      Checked {
+         index: state.event_count.saturating_sub(1),
+         checksum: state.checksum.unwrap_or_else(|| state.game.state_hash()),
+         event,
+      }
+   }
+
+   /// Processes an [`Event`](Play::Event) stamped by [`checked`](Self::checked) on another [`Host`], then compares the
+   /// resulting game state against the sender's checksum. If they differ, returns a [`Desync`] identifying the
+   /// offending event. Note that the event will already have been applied in this case.
+   /// 
+   /// Returns `Ok(false)` if a [`Middleware`] layer dropped the event, or `Ok(true)` otherwise.
+   /// 
+   pub fn process_checked(&self, checked : Checked<<Game as Play>::Event>) -> Result<bool, Desync> where
+      Game : StateHash,
+   {
+      let Checked { index, checksum, mut event } = checked;
+      let processed = self.process_event(&mut event);
+
+      let actual = self.with_game(|game| game.state_hash());
+      if actual != checksum {
+         return Err(Desync { index, expected: checksum, actual });
+      }
+
+      Ok(processed)
    }
 }
 
@@ -161,4 +397,20 @@ impl<Game> Clone for Host<Game> where
    fn clone(&self) -> Self {
       Self { state: self.state.clone() }
    }
-}
\ No newline at end of file
+}
+
+/// Adapts a game coroutine so that it can be resumed with plain [`Input`s](Play::Input). Note that the input supplied
+/// when the coroutine is first resumed is discarded, since the game has not asked for anything yet.
+struct Inputs<C>(C);
+
+impl<C, Input> Coroutine for Inputs<C> where
+   C : Coroutine<Resume = Resume<Input>> + Unpin,
+{
+   type Yield = C::Yield;
+   type Resume = Input;
+   type Return = C::Return;
+
+   fn resume_with(self : Pin<&mut Self>, input : Input) -> GeneratorState<Self::Yield, Self::Return> {
+      Pin::new(&mut self.get_mut().0).resume_with(Resume::Input(input))
+   }
+}
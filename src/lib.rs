@@ -2,13 +2,59 @@
 //
 // SPDX-License-Identifier: MIT
 
+pub mod agent;
+pub use agent::Agent;
+
+pub mod checksum;
+pub use checksum::StateHash;
+
+pub mod dynamic;
+pub use dynamic::DynSession;
+
 pub mod host;
 pub use host::Host;
 
+pub mod middleware;
+pub use middleware::Middleware;
+
+pub mod lockstep;
+pub mod mcts;
+pub mod net;
+pub mod prediction;
+pub mod redact;
+pub use redact::Redact;
+
+pub mod replication;
+pub mod rng;
+
+pub mod scheduler;
+pub mod search;
+
+pub mod seat;
+pub use seat::{Seat, Seated, Seats};
+
+pub mod session;
+pub use session::Session;
+
+pub mod simulate;
+
+pub mod standings;
+pub use standings::{Ranked, Standings};
+
+pub mod testing;
+pub mod tournament;
+
+pub mod trigger;
+pub use trigger::Triggers;
+
+pub mod turn;
+pub use turn::{TakeTurns, TurnOrder};
+
 #[cfg(test)]
+#[allow(clippy::manual_async_fn)]
 mod tests;
 
-use std::future::Future;
+use std::{cell::Cell, collections::HashMap, future::Future};
 
 pub use genawaiter;
 use genawaiter::rc::Co;
@@ -19,7 +65,22 @@ pub struct Context<Game> where
    Game : Play,
 {
    pub host : Host<Game>,
-   co : Co<Game::Event, Game::Input>,
+   co : Co<Game::Event, Resume<Game::Input>>,
+   has_passed_turn : Cell<bool>,
+}
+
+/// The value passed into a game coroutine whenever it is resumed.
+#[derive(Clone)]
+pub(crate) enum Resume<Input> {
+   /// Starts the coroutine. This is never seen by the game itself, since the coroutine has not yet reached its first
+   /// `await` point.
+   Start,
+
+   /// Resumes the coroutine with an [`Input`](Play::Input) for the event it most recently yielded.
+   Input(Input),
+
+   /// Resumes the coroutine with one [`Input`](Play::Input) from each seat asked via [`Context::ask_all`].
+   Inputs(HashMap<Seat, Input>),
 }
 
 impl<Game> Context<Game> where
@@ -36,17 +97,136 @@ impl<Game> Context<Game> where
       self.yield_event(Default::default())
    }
 
-   /// Raises an [`Event`](Play::Event) to be processed outside of the turn-based game loop. The game itself will have
-   /// the chance to react with [`handle_event`](Play::handle_event) before broadcasting.
+   /// Raises an [`Event`](Play::Event) to be processed outside of the turn-based game loop. The event first passes
+   /// through any [`Middleware`] registered on the [`Host`], then the game itself will have the chance to react with
+   /// [`handle_event`](Play::handle_event) before broadcasting. The event is processed as soon as this function is
+   /// called, before the returned `Future` is first polled.
    /// 
    /// ⚠️ **IMPORTANT:** Please remember to immediately `await` the `Future` returned by this function.
    /// 
-   pub fn yield_event(&self, mut event : Game::Event) -> impl Future<Output = Game::Input> + '_ {
+   /// # Safety
+   /// The returned `Future` will panic if a [`Middleware`] layer drops the event, since there is no way to produce an
+   /// [`Input`](Play::Input) without suspending the coroutine. If the host may veto events, use
+   /// [`try_yield_event`](Self::try_yield_event) instead.
+   /// 
+   pub fn yield_event(&self, event : Game::Event) -> impl Future<Output = Game::Input> + '_ {
+      let input = self.try_yield_event(event);
+      async move { input.await.expect("event was dropped by middleware") }
+   }
+
+   /// Like [`yield_event`](Self::yield_event), except that the event may be vetoed by a [`Middleware`] layer. If the
+   /// event is dropped, [`handle_event`](Play::handle_event) is **not** called, the coroutine is **not** suspended, and
+   /// the returned `Future` resolves immediately to [`None`]. Otherwise, resolves to the [`Input`](Play::Input)
+   /// supplied when the game is resumed.
+   /// 
+   /// Any follow-up events queued by [`trigger`](Play::trigger) are processed and yielded in order before the
+   /// coroutine continues. The [`Input`](Play::Input) returned is always the one supplied for `event` itself, and any
+   /// inputs supplied for the follow-up events are discarded.
+   /// 
+   /// Just like with [`yield_event`](Self::yield_event), the event is processed (and any follow-up events are queued)
+   /// as soon as this function is called.
+   /// 
+   /// ⚠️ **IMPORTANT:** Please remember to immediately `await` the `Future` returned by this function.
+   /// 
+   pub fn try_yield_event(&self, mut event : Game::Event) -> impl Future<Output = Option<Game::Input>> + '_ {
       // Allow the game to update itself in response to the event being emitted.
-      self.host.process_event(&mut event);
+      let is_processed = self.host.process_event(&mut event);
+      if is_processed {
+         // Give the game a chance to queue up follow-up events before the UI sees the original event.
+         self.host.trigger(&event, 0);
+      }
+
+      async move {
+         if !is_processed {
+            return None;
+         }
 
-      // "Yield" the event by returning a Future that will wait for the coroutine to be resumed.
-      self.co.yield_(event)
+         // "Yield" the event by waiting for the coroutine to be resumed.
+         let input = self.suspend(event).await;
+
+         // Flush any triggered events before returning control to the game.
+         self.flush_triggers().await;
+         Some(input)
+      }
+   }
+
+   /// Raises an [`Event`](Play::Event) that must be answered by **every** one of `seats` at once, e.g. to collect
+   /// sealed bids or simultaneous moves. The event is processed just like with [`yield_event`](Self::yield_event), but
+   /// the game stays suspended until each seat has submitted an [`Input`](Play::Input) via
+   /// [`Session::resume_as`]. None of the inputs are revealed to the game until all of them are in.
+   /// 
+   /// Any follow-up events queued by [`trigger`](Play::trigger) are yielded after the inputs have been collected, and
+   /// any inputs supplied for them are discarded.
+   /// 
+   /// ⚠️ **IMPORTANT:** Please remember to immediately `await` the `Future` returned by this function.
+   /// 
+   /// # Safety
+   /// The returned `Future` will panic if `seats` is empty, if a [`Middleware`] layer drops the event, or if the game
+   /// is not being driven by a [`Session`] (since a bare coroutine can only be resumed with a single input).
+   /// 
+   pub async fn ask_all(&self, seats : Seats, mut event : Game::Event) -> HashMap<Seat, Game::Input> {
+      assert!(!seats.is_empty(), "at least one seat must be asked for input");
+
+      if !self.host.process_event(&mut event) {
+         panic!("event was dropped by middleware");
+      }
+
+      self.host.trigger(&event, 0);
+
+      // Let the session know that it needs to collect an input from each seat before resuming.
+      self.host.set_asking(Some(seats));
+      let inputs = match self.co.yield_(event).await {
+         Resume::Inputs(inputs) => inputs,
+         _ => panic!("simultaneous prompts can only be answered via a `Session`"),
+      };
+
+      self.flush_triggers().await;
+      inputs
+   }
+
+   /// Passes the turn to the next player in the game's [`TurnOrder`], then yields the [`Event`](Play::Event) returned
+   /// by [`TakeTurns::turn_changed`] just like [`yield_event`](Self::yield_event). Resolves to the new player together
+   /// with the [`Input`](Play::Input) supplied for the event, or to [`None`] if every player has been eliminated.
+   /// 
+   /// The first call made by a coroutine doesn't pass the turn if a turn is already in progress, and picks up on the
+   /// current player's turn instead. This way, a copy of a game made in the middle of a turn (e.g. by [`search`]) can
+   /// be played from the start without skipping a player.
+   /// 
+   /// ⚠️ **IMPORTANT:** Please remember to immediately `await` the `Future` returned by this function.
+   /// 
+   pub async fn next_turn(&self) -> Option<(Game::Player, Game::Input)> where
+      Game : TakeTurns,
+   {
+      let player = {
+         let mut game = self.host.borrow_game_mut();
+         let turn_order = game.turn_order();
+         if self.has_passed_turn.replace(true) { turn_order.advance() } else { turn_order.resume() }?.clone()
+      };
+
+      let event = self.host.with_game(|game| game.turn_changed(&player));
+      let input = self.yield_event(event).await;
+      Some((player, input))
+   }
+
+   /// Processes and yields each follow-up event queued by [`trigger`](Play::trigger), in order. Any inputs supplied
+   /// for these events are discarded.
+   async fn flush_triggers(&self) {
+      while let Some((depth, mut event)) = self.host.pop_trigger() {
+         if self.host.process_event(&mut event) {
+            self.host.trigger(&event, depth);
+            self.suspend(event).await;
+         }
+      }
+   }
+
+   /// Suspends the coroutine, yielding an [`Event`](Play::Event) that has already been processed, until it is resumed
+   /// with an [`Input`](Play::Input).
+   async fn suspend(&self, event : Game::Event) -> Game::Input {
+      match self.co.yield_(event).await {
+         Resume::Input(input) => input,
+         Resume::Inputs(_) => unreachable!("game coroutine was resumed with too many inputs"),
+         Resume::Start => unreachable!("game coroutine was started twice"),
+      }
    }
 }
 
@@ -75,4 +255,36 @@ pub trait Play : Sized {
    /// [`play`](Play::play) or supplied externally via [`Host::process_event`]. The event is mutable so that the
    /// implementation can consume or otherwise modify it, if desired.
    fn handle_event(&mut self, _event : &mut <Self as Play>::Event) { }
+
+   /// Allows the game to queue follow-up [`Event`s](Play::Event) (e.g. triggered abilities) in response to an event
+   /// emitted by [`play`](Play::play), after it has been passed to [`handle_event`](Play::handle_event). Queued events
+   /// are processed and yielded in order before the coroutine continues, and may trigger further events of their own.
+   /// Any inputs supplied for queued events are discarded, so the game only ever sees the input for its own event.
+   /// The game itself cannot be modified here; any state changes belong in [`handle_event`](Play::handle_event).
+   /// 
+   /// This is **not** called for events supplied externally via [`Host::process_event`], since a server replicating
+   /// its events will also replicate any follow-up events it yields.
+   /// 
+   fn trigger(&self, _event : &<Self as Play>::Event, _triggers : &mut Triggers<Self>) { }
+}
+
+/// Extension of [`Play`] for games that can reject an [`Event`](Play::Event) that makes no sense in the current game
+/// state. This is mainly useful for networked games, where a client applying events replicated from a server via
+/// [`Host::try_process_event`] can detect that its local state has diverged from the server's.
+pub trait TryHandleEvent : Play {
+   /// The error returned when an [`Event`](Play::Event) is rejected.
+   type EventError;
+
+   /// Fallible counterpart to [`handle_event`](Play::handle_event). Implementations **must** leave the game state
+   /// unchanged when returning an error.
+   fn try_handle_event(&mut self, event : &mut <Self as Play>::Event) -> Result<(), Self::EventError>;
+}
+
+/// Extension of [`Play`] for games that can enumerate every [`Input`](Play::Input) that is valid at a given prompt.
+/// This lets frontends show which moves are playable, lets bots and fuzzers pick moves without trial and error, and
+/// lets a [`Session`] reject illegal inputs before they reach the game (see [`Session::validate_inputs`]).
+pub trait LegalMoves : Play {
+   /// Iterates over every input that is valid in response to `pending`, the [`Event`](Play::Event) the game is
+   /// currently waiting on.
+   fn legal_inputs(&self, pending : &Self::Event) -> impl Iterator<Item = Self::Input>;
 }
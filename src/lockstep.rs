@@ -0,0 +1,203 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Peer-to-peer play of a deterministic game, where peers only exchange [`Input`s](Play::Input).
+//!
+//! Every peer runs the game in its own [`Session`], and a [`Lockstep`] makes sure that each of them resumes it with
+//! the same input on every turn. Prompts raised via [`Context::ask_all`](crate::Context::ask_all) are supported, but
+//! are answered one seat at a time in seat order, and each input is broadcast as soon as it is submitted. This means
+//! that seats answering later can see the inputs of seats that answered before them, so `ask_all` can't be used for
+//! secret, simultaneous choices in lockstep. Such games are better served by a server (see
+//! [`replication`](crate::replication)).
+
+use std::{collections::BTreeMap, io};
+
+use crate::{
+   net::{Transport, Wire},
+   replication::Disconnected,
+   session::SessionError,
+   Play, Seat, Seated, Session,
+};
+
+#[derive(Debug)]
+pub enum LockstepError {
+   /// The local player tried to submit an input on a turn that belongs to another seat.
+   NotYourTurn {
+      /// The seat whose input is authoritative for the current turn.
+      owner : Seat,
+   },
+
+   /// A peer sent an input for a turn that it does not own.
+   UnexpectedInput {
+      /// The turn the input was sent for.
+      turn : u64,
+
+      /// The seat that sent the input.
+      seat : Seat,
+   },
+
+   /// The local input was played, but could not be sent to some peers, which were disconnected. Every other peer did
+   /// receive the input.
+   Unreachable(Vec<Disconnected>),
+
+   /// The underlying [`Session`] could not be resumed.
+   Session(SessionError),
+
+   /// A [`Transport`] failed, or a peer sent a malformed frame.
+   Io(io::Error),
+}
+
+impl From<SessionError> for LockstepError {
+   fn from(err : SessionError) -> Self {
+      Self::Session(err)
+   }
+}
+
+impl From<io::Error> for LockstepError {
+   fn from(err : io::Error) -> Self {
+      Self::Io(err)
+   }
+}
+
+/// Runs a deterministic game in lockstep with one or more remote peers. Every peer runs [`play`](Play::play) locally
+/// in its own [`Session`], and only [`Input`s](Play::Input) are exchanged over the network.
+///
+/// Each pending [`Event`](Play::Event) is a "turn" owned by a single [`Seat`], namely the lowest-numbered seat in
+/// [`Seated::seats_to_answer`] (or the lowest-numbered seat at the table, if any seat may answer). The peer sitting in
+/// that seat submits its input via [`submit`](Self::submit), which broadcasts it to every other peer. All other peers
+/// wait in [`poll`](Self::poll) until the input arrives. The session is only ever resumed once the authoritative input
+/// for the current turn is known, so every peer sees the exact same sequence of events.
+pub struct Lockstep<Game, T> where
+   Game : Play + 'static,
+{
+   session : Session<Game>,
+   local : Seat,
+   peers : Vec<(Seat, T)>,
+   turn : u64,
+   received : BTreeMap<(u64, Seat), Game::Input>,
+}
+
+impl<Game, T> Lockstep<Game, T> where
+   Game : Seated + 'static,
+   Game::Input : Wire,
+   T : Transport,
+{
+   /// Creates a new [`Lockstep`] game for the local player sitting in `local`. Remote peers are added with
+   /// [`add_peer`](Self::add_peer).
+   pub fn new(session : Session<Game>, local : Seat) -> Self {
+      Self {
+         session,
+         local,
+         peers: Vec::new(),
+         turn: 0,
+         received: BTreeMap::new(),
+      }
+   }
+
+   /// Connects a remote peer sitting in `seat`. Peers that can't be reached are disconnected by
+   /// [`submit`](Self::submit), and can be reconnected with this function.
+   pub fn add_peer(&mut self, seat : Seat, transport : T) {
+      self.peers.push((seat, transport));
+   }
+
+   /// Returns the local [`Session`].
+   pub fn session(&self) -> &Session<Game> {
+      &self.session
+   }
+
+   /// Returns the [`Seat`] of the local player.
+   pub fn local_seat(&self) -> Seat {
+      self.local
+   }
+
+   /// Returns the number of turns that have been played so far.
+   pub fn turn(&self) -> u64 {
+      self.turn
+   }
+
+   /// Returns the [`Seat`] that owns the current turn, or [`None`] if the game is over.
+   pub fn current_owner(&self) -> Option<Seat> {
+      if self.session.is_complete() {
+         return None;
+      }
+
+      let table = self.peers.iter().map(|(seat, _)| *seat).chain([self.local]);
+      self.session.seats_to_answer().first().or_else(|| table.min())
+   }
+
+   /// Returns `true` if the local player owns the current turn.
+   pub fn is_local_turn(&self) -> bool {
+      self.current_owner() == Some(self.local)
+   }
+
+   /// Submits the local player's [`Input`](Play::Input) for the current turn, resuming the local session and then
+   /// broadcasting the input to every peer. Fails with [`LockstepError::NotYourTurn`] if another seat owns the turn. If
+   /// the local session rejects the input, it is **not** sent, so peers never see inputs that could not be played.
+   ///
+   /// Once the input has been played, it is sent to every peer, even if sending to some of them fails. Peers that
+   /// could not be reached are disconnected and reported via [`LockstepError::Unreachable`]. The turn still counts as
+   /// played in that case, since every other peer has moved on. A disconnected peer must be brought back up to date
+   /// (e.g. by replaying its missed inputs) before it is added again with [`add_peer`](Self::add_peer).
+   pub fn submit(&mut self, input : Game::Input) -> Result<(), LockstepError> {
+      let owner = self.current_owner().ok_or(SessionError::Complete)?;
+      if owner != self.local {
+         return Err(LockstepError::NotYourTurn { owner });
+      }
+
+      let mut frame = self.turn.to_bytes();
+      input.encode(&mut frame);
+      self.advance(owner, input)?;
+
+      let mut disconnected = Vec::new();
+      self.peers.retain_mut(|(seat, transport)| match transport.send(&frame) {
+         Ok(()) => true,
+         Err(error) => {
+            disconnected.push(Disconnected { seat: *seat, error });
+            false
+         },
+      });
+
+      if disconnected.is_empty() { Ok(()) } else { Err(LockstepError::Unreachable(disconnected)) }
+   }
+
+   /// Receives inputs from remote peers, resuming the local session for as long as the authoritative input for the
+   /// current turn is known. Returns the number of turns that were played.
+   ///
+   /// Inputs are kept per seat, so an input sent by the wrong peer never replaces the one sent by the owner of the
+   /// turn. If a peer sends an input for the current turn without owning it, that input is discarded and reported via
+   /// [`LockstepError::UnexpectedInput`]. Any input from the owner is kept, and will be played by the next call.
+   pub fn poll(&mut self) -> Result<usize, LockstepError> {
+      for (seat, transport) in self.peers.iter_mut() {
+         while let Some(frame) = transport.try_recv()? {
+            let (turn, input) = <(u64, Game::Input)>::from_bytes(&frame).map_err(io::Error::from)?;
+            if turn >= self.turn {
+               self.received.insert((turn, *seat), input);
+            }
+         }
+      }
+
+      let mut played = 0;
+      while let Some(owner) = self.current_owner() {
+         let this_turn = (self.turn, Seat(0))..(self.turn + 1, Seat(0));
+         let unexpected = self.received.range(this_turn).map(|(&key, _)| key).find(|&(_, seat)| seat != owner);
+         if let Some((turn, seat)) = unexpected {
+            self.received.remove(&(turn, seat));
+            return Err(LockstepError::UnexpectedInput { turn, seat });
+         }
+
+         let Some(input) = self.received.remove(&(self.turn, owner)) else { break };
+         self.advance(owner, input)?;
+         played += 1;
+      }
+
+      Ok(played)
+   }
+
+   /// Resumes the local session with the authoritative input for the current turn, submitted by its `owner`.
+   fn advance(&mut self, owner : Seat, input : Game::Input) -> Result<(), LockstepError> {
+      self.session.resume_as(owner, input)?;
+      self.turn += 1;
+      Ok(())
+   }
+}
@@ -0,0 +1,328 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::time::{Duration, Instant};
+
+use crate::{rng::Rng, Agent, Host, LegalMoves, Play, Ranked, Seat, Seated, Session, Standings};
+
+/// Extension of [`Play`] for games with hidden information (e.g. shuffled decks or other players' hands), allowing
+/// [`Mcts`] to search over plausible versions of the game rather than peeking at information a player can't see.
+pub trait Determinize : Play {
+   /// Randomly resamples everything in `self` that `observer` cannot know about, e.g. by reshuffling the cards that
+   /// `observer` has not seen. `self` is a copy of the current game state, from which a game will be played out.
+   ///
+   /// Resampled states should stay consistent with everything `observer` has seen so far, e.g. by leaving `observer`'s
+   /// own hand alone.
+   fn determinize(&mut self, observer : Seat, rng : &mut Rng);
+}
+
+/// A source of elapsed time, used to enforce a [`Budget::Time`]. Tests can inject their own clock to make searches
+/// deterministic.
+pub trait Clock {
+   /// Returns the time elapsed since some fixed point in the past.
+   fn now(&self) -> Duration;
+}
+
+/// A [`Clock`] measuring real time since it was created.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+   start : Instant,
+}
+
+impl Default for SystemClock {
+   fn default() -> Self {
+      Self { start: Instant::now() }
+   }
+}
+
+impl Clock for SystemClock {
+   fn now(&self) -> Duration {
+      self.start.elapsed()
+   }
+}
+
+/// How much work an [`Mcts`] search may do before choosing a move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Budget {
+   /// Play out exactly this many games, or a single game if this is `0`.
+   Iterations(usize),
+
+   /// Keep playing out games until this much time has passed, according to the search's [`Clock`]. At least one game
+   /// is always played out.
+   Time(Duration),
+}
+
+/// The move chosen by an [`Mcts`] search, along with some statistics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Playouts<Input> {
+   /// The input to play.
+   pub input : Input,
+
+   /// The number of played out games that started with [`input`](Self::input).
+   pub visits : usize,
+
+   /// The average reward of those games, from `0.0` (always last) to `1.0` (always first).
+   pub mean_reward : f64,
+
+   /// The total number of games played out during the search.
+   pub iterations : usize,
+}
+
+/// Resamples hidden information in a copy of the game state. See [`Determinize`].
+type DeterminizeFn<Game> = fn(&mut Game, Seat, &mut Rng);
+
+/// Monte Carlo Tree Search (UCT) over any game whose [`Outcome`](Play::Outcome) is [`Ranked`]. Unlike
+/// [`minimax`](crate::search::minimax), it needs no evaluation function, and copes with huge numbers of legal moves.
+///
+/// Each iteration starts a fresh [`Host`] from a copy of the game state, follows the most promising known moves, tries
+/// one new move, and then plays random legal moves until the game is over. As with
+/// [`minimax`](crate::search::minimax), the game's [`play`](Play::play) coroutine **must** pick up where it left off
+/// when started from any state it yields. Each player is rewarded by where they placed in the resulting [`Standings`]:
+/// `1.0` for sole first place, `0.0` for sole last place, and `0.5` for games that hit the rollout limit without
+/// finishing. Moves made in response to prompts that no seat has to answer (see [`Seated`]) are credited to whoever
+/// moved last, or to the searching seat if nobody has moved yet.
+///
+/// For games with hidden information, enable [`determinized`](Self::determinized) search, so that each iteration
+/// plays out a different plausible version of the game (see [`Determinize`]). Since the legal moves may differ between
+/// versions, each iteration only follows (and tries) moves that are legal in its own version.
+///
+/// An [`Mcts`] is also an [`Agent`], which searches from a copy of the game state it is shown.
+pub struct Mcts<Game, C = SystemClock> where
+   Game : Play,
+{
+   budget : Budget,
+   clock : C,
+   rng : Rng,
+   exploration : f64,
+   max_rollout : usize,
+   determinize : Option<DeterminizeFn<Game>>,
+}
+
+impl<Game> Mcts<Game> where
+   Game : Play,
+{
+   /// Creates a new search with the given `budget`, using `seed` to make random choices. Time is measured with a
+   /// [`SystemClock`].
+   pub fn new(budget : Budget, seed : u64) -> Self {
+      Self {
+         budget,
+         clock: SystemClock::default(),
+         rng: Rng::new(seed),
+         exploration: Self::DEFAULT_EXPLORATION,
+         max_rollout: Self::DEFAULT_MAX_ROLLOUT,
+         determinize: None,
+      }
+   }
+}
+
+impl<Game, C> Mcts<Game, C> where
+   Game : Play,
+{
+   /// The default weight given to exploring rarely visited moves over exploiting good ones.
+   pub const DEFAULT_EXPLORATION : f64 = std::f64::consts::SQRT_2;
+
+   /// The default number of random moves played during a single rollout before giving up on finishing the game.
+   pub const DEFAULT_MAX_ROLLOUT : usize = 1000;
+
+   /// Replaces the [`Clock`] used to enforce a [`Budget::Time`].
+   pub fn with_clock<D>(self, clock : D) -> Mcts<Game, D> {
+      Mcts {
+         budget: self.budget,
+         clock,
+         rng: self.rng,
+         exploration: self.exploration,
+         max_rollout: self.max_rollout,
+         determinize: self.determinize,
+      }
+   }
+
+   /// Enables determinization, resampling hidden information via [`Determinize`] at the start of every iteration.
+   pub fn determinized(mut self) -> Self where
+      Game : Determinize,
+   {
+      self.determinize = Some(Game::determinize);
+      self
+   }
+
+   /// Sets the weight given to exploring rarely visited moves over exploiting good ones. Defaults to
+   /// [`DEFAULT_EXPLORATION`](Self::DEFAULT_EXPLORATION).
+   pub fn set_exploration(&mut self, exploration : f64) {
+      self.exploration = exploration;
+   }
+
+   /// Sets the number of random moves played during a single rollout before giving up on finishing the game. Defaults
+   /// to [`DEFAULT_MAX_ROLLOUT`](Self::DEFAULT_MAX_ROLLOUT).
+   pub fn set_max_rollout(&mut self, max_rollout : usize) {
+      self.max_rollout = max_rollout;
+   }
+}
+
+impl<Game, C> Mcts<Game, C> where
+   Game : LegalMoves + Seated + Clone + 'static,
+   Game::Input : Clone + PartialEq,
+   Game::Outcome : Ranked,
+   C : Clock,
+{
+   /// Searches for the best [`Input`](Play::Input) for `seat` in response to the prompt pending in `root`, within the
+   /// search's [`Budget`]. Returns [`None`] if the game is over or there are no legal inputs.
+   pub fn search(&mut self, root : &Session<Game>, seat : Seat) -> Option<Playouts<Game::Input>> {
+      if root.legal_inputs().is_empty() {
+         return None;
+      }
+
+      let mut tree = vec![Node::new(None, seat)];
+      let start = self.clock.now();
+      let mut iterations = 0;
+
+      loop {
+         let is_done = match self.budget {
+            Budget::Iterations(count) => iterations >= count.max(1),
+            Budget::Time(limit) => iterations > 0 && self.clock.now().saturating_sub(start) >= limit,
+         };
+         if is_done {
+            break;
+         }
+
+         self.iterate(root, seat, &mut tree);
+         iterations += 1;
+      }
+
+      let root_node = &tree[0];
+      let best = root_node.children.iter().map(|&index| &tree[index]).max_by_key(|child| child.visits)?;
+      Some(Playouts {
+         input: best.input.clone()?,
+         visits: best.visits,
+         mean_reward: best.reward / best.visits.max(1) as f64,
+         iterations,
+      })
+   }
+
+   /// Plays out a single game, growing the `tree` by one node.
+   fn iterate(&mut self, root : &Session<Game>, seat : Seat, tree : &mut Vec<Node<Game::Input>>) {
+      let mut game = root.host().clone_game();
+      if let Some(determinize) = self.determinize {
+         determinize(&mut game, seat, &mut self.rng);
+      }
+      let mut session = Session::new(Host::new(game)).expect("a new host can always be played");
+
+      // Select the most promising known moves, then expand the tree with a new one.
+      let mut path = vec![0];
+      let mut index = 0;
+      while !session.is_complete() {
+         // NOTE: Determinized versions of the game may not share the same legal moves, so check them every time.
+         let legal = session.legal_inputs();
+         let children = &tree[index].children;
+         let is_tried = |input : &Game::Input| children.iter().any(|&child| tree[child].input.as_ref() == Some(input));
+         let mut untried : Vec<_> = legal.iter().filter(|input| !is_tried(input)).collect();
+         if !untried.is_empty() {
+            let input = untried.swap_remove(self.rng.below(untried.len())).clone();
+            let mover = session.seats_to_answer().first().unwrap_or(tree[index].mover);
+            if session.resume(input.clone()).is_err() {
+               break;
+            }
+
+            tree.push(Node::new(Some(input), mover));
+            let child = tree.len() - 1;
+            tree[index].children.push(child);
+            path.push(child);
+            break;
+         }
+
+         let Some(child) = self.select(tree, index, &legal) else {
+            break;
+         };
+
+         let input = tree[child].input.clone().expect("only the root has no input");
+         if session.resume(input).is_err() {
+            break;
+         }
+
+         path.push(child);
+         index = child;
+      }
+
+      // Play random moves until the game is over.
+      for _ in 0..self.max_rollout {
+         if session.is_complete() {
+            break;
+         }
+
+         let inputs = session.legal_inputs();
+         let Some(input) = self.rng.pick(&inputs) else {
+            break;
+         };
+         if session.resume(input.clone()).is_err() {
+            break;
+         }
+      }
+
+      let standings = session.outcome().map(Ranked::standings);
+      for index in path {
+         let node = &mut tree[index];
+         node.visits += 1;
+         node.reward += reward(standings.as_ref(), node.mover);
+      }
+   }
+
+   /// Picks the child of `tree[index]` with the best upper confidence bound (UCB1), out of those whose input is one of
+   /// the `legal` inputs.
+   fn select(&self, tree : &[Node<Game::Input>], index : usize, legal : &[Game::Input]) -> Option<usize> {
+      let parent_visits = (tree[index].visits.max(1) as f64).ln();
+      let ucb = |child : &Node<Game::Input>| {
+         let visits = child.visits.max(1) as f64;
+         child.reward / visits + self.exploration * (parent_visits / visits).sqrt()
+      };
+
+      tree[index].children.iter().copied()
+         .filter(|&child| tree[child].input.as_ref().is_some_and(|input| legal.contains(input)))
+         .max_by(|&a, &b| ucb(&tree[a]).total_cmp(&ucb(&tree[b])))
+   }
+}
+
+impl<Game, C> Agent<Game> for Mcts<Game, C> where
+   Game : LegalMoves + Seated + Clone + 'static,
+   Game::Input : Clone + PartialEq,
+   Game::Outcome : Ranked,
+   C : Clock,
+{
+   fn choose(&mut self, game : &Game, prompt : &Game::Event) -> Game::Input {
+      let seat = game.seats_to_answer(prompt).first().expect("agents only answer prompts meant for their seat");
+      let root = Session::new(Host::new(game.clone())).expect("a new host can always be played");
+      self.search(&root, seat).expect("a pending prompt has at least one legal input").input
+   }
+}
+
+/// A single move in the search tree.
+struct Node<Input> {
+   input : Option<Input>,
+   mover : Seat,
+   children : Vec<usize>,
+   visits : usize,
+   reward : f64,
+}
+
+impl<Input> Node<Input> {
+   /// Creates a node for `input`, as played by `mover`.
+   fn new(input : Option<Input>, mover : Seat) -> Self {
+      Self { input, mover, children: Vec::new(), visits: 0, reward: 0.0 }
+   }
+}
+
+/// Rewards `seat` for where it placed in `standings`, between `0.0` for sole last place and `1.0` for sole first
+/// place. Ties count as half a win against each other tied player.
+fn reward(standings : Option<&Standings>, seat : Seat) -> f64 {
+   let Some(standings) = standings else {
+      return 0.5;
+   };
+   let Some(place) = standings.place_of(&seat) else {
+      return 0.5;
+   };
+   if standings.len() < 2 {
+      return 1.0;
+   }
+
+   let behind = standings.iter().filter(|placement| placement.place > place).count();
+   let tied = standings.iter().filter(|placement| placement.place == place).count() - 1;
+   (behind as f64 + tied as f64 * 0.5) / (standings.len() - 1) as f64
+}
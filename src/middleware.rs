@@ -0,0 +1,48 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::Play;
+
+/// Decision made by a [`Middleware`] layer about whether an [`Event`](Play::Event) should keep moving through the
+/// pipeline.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Intercept {
+   /// Pass the (possibly modified) event on to the next layer, and eventually to [`handle_event`](Play::handle_event).
+   #[default]
+   Continue,
+
+   /// Veto the event. No further layers will see it, and [`handle_event`](Play::handle_event) will **not** be called.
+   /// See [`Middleware`] for which events may safely be dropped.
+   Drop,
+}
+
+/// A single layer in the ordered pipeline that every [`Event`](Play::Event) passes through on its way to
+/// [`handle_event`](Play::handle_event). Layers are added with [`Host::push_middleware`](crate::Host::push_middleware)
+/// and run in the order they were added.
+///
+/// Each layer may inspect the event (along with a read-only view of the game state), mutate it in place, replace it
+/// outright by assigning through the `&mut` reference, or veto it by returning [`Intercept::Drop`].
+///
+/// ⚠️ **IMPORTANT:** Vetoing an event that the game raised via [`Context::yield_event`](crate::Context::yield_event),
+/// [`Context::next_turn`](crate::Context::next_turn) or [`Context::ask_all`](crate::Context::ask_all) will panic,
+/// since the game is waiting on an [`Input`](Play::Input) for it that will never arrive. Only games that raise events
+/// via [`Context::try_yield_event`](crate::Context::try_yield_event) can cope with them being dropped.
+///
+/// Any `FnMut(&Game, &mut Game::Event) -> Intercept` closure can be used as a layer.
+pub trait Middleware<Game> where
+   Game : Play,
+{
+   /// Called for each [`Event`](Play::Event) before it reaches [`handle_event`](Play::handle_event). The game state is
+   /// read-only here, since the game has not yet had a chance to react to the event.
+   fn intercept(&mut self, game : &Game, event : &mut Game::Event) -> Intercept;
+}
+
+impl<Game, F> Middleware<Game> for F where
+   Game : Play,
+   F : FnMut(&Game, &mut Game::Event) -> Intercept,
+{
+   fn intercept(&mut self, game : &Game, event : &mut Game::Event) -> Intercept {
+      self(game, event)
+   }
+}
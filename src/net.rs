@@ -0,0 +1,303 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{
+   io::{self, Read, Write},
+   net::TcpStream,
+   sync::mpsc::{self, Receiver, Sender, TryRecvError},
+};
+
+/// Returned when a frame received over a [`Transport`] could not be decoded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WireError;
+
+impl std::fmt::Display for WireError {
+   fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.write_str("malformed or truncated frame")
+   }
+}
+
+impl std::error::Error for WireError { }
+
+impl From<WireError> for io::Error {
+   fn from(err : WireError) -> Self {
+      io::Error::new(io::ErrorKind::InvalidData, err)
+   }
+}
+
+/// A minimal binary encoding for values that need to be sent over a [`Transport`], e.g. [`Event`s](crate::Play::Event)
+/// and [`Input`s](crate::Play::Input). This is implemented for most primitive types, [`String`], [`Option`], [`Vec`]
+/// and tuples. Games are expected to implement it for their own types by encoding each field in turn.
+pub trait Wire : Sized {
+   /// Appends the encoded form of `self` to `out`.
+   fn encode(&self, out : &mut Vec<u8>);
+
+   /// Decodes a value from the front of `input`, advancing it past the bytes that were consumed.
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError>;
+
+   /// Convenience function that encodes `self` into a new buffer.
+   fn to_bytes(&self) -> Vec<u8> {
+      let mut out = Vec::new();
+      self.encode(&mut out);
+      out
+   }
+
+   /// Convenience function that decodes a value from `bytes`, failing if any bytes are left over.
+   fn from_bytes(mut bytes : &[u8]) -> Result<Self, WireError> {
+      let value = Self::decode(&mut bytes)?;
+      if bytes.is_empty() { Ok(value) } else { Err(WireError) }
+   }
+}
+
+/// Splits `len` bytes off the front of `input`.
+fn take<'a>(input : &mut &'a [u8], len : usize) -> Result<&'a [u8], WireError> {
+   if input.len() < len {
+      return Err(WireError);
+   }
+
+   let (head, tail) = input.split_at(len);
+   *input = tail;
+   Ok(head)
+}
+
+macro_rules! impl_wire_for_int {
+   ($($int:ty),*) => {
+      $(
+         impl Wire for $int {
+            fn encode(&self, out : &mut Vec<u8>) {
+               out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+               let bytes = take(input, std::mem::size_of::<$int>())?;
+               Ok(<$int>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+         }
+      )*
+   };
+}
+
+impl_wire_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Wire for usize {
+   fn encode(&self, out : &mut Vec<u8>) {
+      (*self as u64).encode(out)
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      u64::decode(input)?.try_into().map_err(|_| WireError)
+   }
+}
+
+impl Wire for bool {
+   fn encode(&self, out : &mut Vec<u8>) {
+      (*self as u8).encode(out)
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      match u8::decode(input)? {
+         0 => Ok(false),
+         1 => Ok(true),
+         _ => Err(WireError),
+      }
+   }
+}
+
+impl Wire for () {
+   fn encode(&self, _out : &mut Vec<u8>) { }
+
+   fn decode(_input : &mut &[u8]) -> Result<Self, WireError> {
+      Ok(())
+   }
+}
+
+impl Wire for String {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.len().encode(out);
+      out.extend_from_slice(self.as_bytes());
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      let len = usize::decode(input)?;
+      let bytes = take(input, len)?;
+      String::from_utf8(bytes.to_vec()).map_err(|_| WireError)
+   }
+}
+
+impl<T> Wire for Option<T> where
+   T : Wire,
+{
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.is_some().encode(out);
+      if let Some(value) = self {
+         value.encode(out);
+      }
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      if bool::decode(input)? { T::decode(input).map(Some) } else { Ok(None) }
+   }
+}
+
+impl<T> Wire for Vec<T> where
+   T : Wire,
+{
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.len().encode(out);
+      for value in self {
+         value.encode(out);
+      }
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      let len = usize::decode(input)?;
+
+      // NOTE: Don't trust the length prefix when reserving memory, since it may be garbage.
+      let mut values = Vec::with_capacity(len.min(input.len()));
+      for _ in 0..len {
+         values.push(T::decode(input)?);
+      }
+      Ok(values)
+   }
+}
+
+macro_rules! impl_wire_for_tuple {
+   ($($name:ident),+) => {
+      impl<$($name),+> Wire for ($($name,)+) where
+         $($name : Wire,)+
+      {
+         #[allow(non_snake_case)]
+         fn encode(&self, out : &mut Vec<u8>) {
+            let ($($name,)+) = self;
+            $($name.encode(out);)+
+         }
+
+         fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+            Ok(($($name::decode(input)?,)+))
+         }
+      }
+   };
+}
+
+impl_wire_for_tuple!(A);
+impl_wire_for_tuple!(A, B);
+impl_wire_for_tuple!(A, B, C);
+impl_wire_for_tuple!(A, B, C, D);
+
+/// A bidirectional, message-oriented connection to a remote peer. Frames are delivered whole, but implementations are
+/// **not** required to deliver them reliably or in order; the [`replication`](crate::replication) layer copes with
+/// both.
+pub trait Transport {
+   /// Sends a single frame to the remote peer.
+   fn send(&mut self, frame : &[u8]) -> io::Result<()>;
+
+   /// Returns the next frame received from the remote peer, if one is available. This **must not** block. Returns an
+   /// error once the connection has been closed and there are no more frames left to receive.
+   fn try_recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// An in-process [`Transport`] backed by a pair of channels. Useful for tests, and for running a server and client in
+/// the same process (e.g. on different threads).
+#[derive(Debug)]
+pub struct MemoryTransport {
+   outgoing : Sender<Vec<u8>>,
+   incoming : Receiver<Vec<u8>>,
+}
+
+impl MemoryTransport {
+   /// Creates two [`MemoryTransport`s](MemoryTransport) connected to each other.
+   pub fn pair() -> (Self, Self) {
+      let (a_out, b_in) = mpsc::channel();
+      let (b_out, a_in) = mpsc::channel();
+      (Self { outgoing: a_out, incoming: a_in }, Self { outgoing: b_out, incoming: b_in })
+   }
+}
+
+impl Transport for MemoryTransport {
+   fn send(&mut self, frame : &[u8]) -> io::Result<()> {
+      self.outgoing.send(frame.to_vec()).map_err(|_| io::ErrorKind::BrokenPipe.into())
+   }
+
+   fn try_recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+      match self.incoming.try_recv() {
+         Ok(frame) => Ok(Some(frame)),
+         Err(TryRecvError::Empty) => Ok(None),
+         Err(TryRecvError::Disconnected) => Err(io::ErrorKind::BrokenPipe.into()),
+      }
+   }
+}
+
+/// A [`Transport`] that sends length-prefixed frames over a [`TcpStream`].
+#[derive(Debug)]
+pub struct TcpTransport {
+   stream : TcpStream,
+   buffer : Vec<u8>,
+   is_closed : bool,
+}
+
+impl TcpTransport {
+   /// Wraps a connected [`TcpStream`]. Writes will block, but reads via [`try_recv`](Transport::try_recv) will not.
+   pub fn new(stream : TcpStream) -> io::Result<Self> {
+      stream.set_nodelay(true)?;
+      Ok(Self { stream, buffer: Vec::new(), is_closed: false })
+   }
+
+   /// Removes the first complete frame from the receive buffer, if there is one.
+   fn next_frame(&mut self) -> Option<Vec<u8>> {
+      let header = self.buffer.get(..4)?;
+      let len = u32::from_le_bytes(header.try_into().unwrap()) as usize;
+      if self.buffer.len() < 4 + len {
+         return None;
+      }
+
+      let frame = self.buffer[4..4 + len].to_vec();
+      self.buffer.drain(..4 + len);
+      Some(frame)
+   }
+
+   /// Reads whatever bytes are available on the socket into the receive buffer without blocking.
+   fn fill_buffer(&mut self) -> io::Result<()> {
+      let mut chunk = [0; 4096];
+      self.stream.set_nonblocking(true)?;
+
+      let result = loop {
+         match self.stream.read(&mut chunk) {
+            Ok(0) => {
+               self.is_closed = true;
+               break Ok(());
+            },
+            Ok(len) => self.buffer.extend_from_slice(&chunk[..len]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => break Err(err),
+         }
+      };
+
+      self.stream.set_nonblocking(false)?;
+      result
+   }
+}
+
+impl Transport for TcpTransport {
+   fn send(&mut self, frame : &[u8]) -> io::Result<()> {
+      let len = u32::try_from(frame.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+      self.stream.write_all(&len.to_le_bytes())?;
+      self.stream.write_all(frame)
+   }
+
+   fn try_recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+      if let Some(frame) = self.next_frame() {
+         return Ok(Some(frame));
+      }
+
+      if !self.is_closed {
+         self.fill_buffer()?;
+         if let Some(frame) = self.next_frame() {
+            return Ok(Some(frame));
+         }
+      }
+
+      if self.is_closed { Err(io::ErrorKind::UnexpectedEof.into()) } else { Ok(None) }
+   }
+}
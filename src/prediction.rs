@@ -0,0 +1,176 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{collections::VecDeque, io};
+
+use crate::{net::{Transport, Wire}, replication::Mirror, Host, Play};
+
+/// Trait for games that can guess which [`Event`s](Play::Event) the server will yield in response to a local
+/// [`Input`](Play::Input), so that a [`Predictor`] can show the results of a move before the server confirms it.
+pub trait Predict : Play + Clone {
+   /// Returns the events the server is expected to yield once it receives `input`, in order. These are applied to a
+   /// copy of the current (authoritative) game state.
+   fn predict(&self, input : &Self::Input) -> Vec<Self::Event>;
+}
+
+/// The result of reconciling predicted events with the authoritative events received from the server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reconciliation<Event> {
+   /// `true` if a prediction turned out to be wrong, and the predicted game state was rolled back. The UI should
+   /// re-render the game state from scratch in this case.
+   pub rolled_back : bool,
+
+   /// Events applied to the predicted game state that the UI has not seen yet. Events that merely confirm a prediction
+   /// are **not** included, unless the state was rolled back, in which case this holds every authoritative event that
+   /// was re-applied on top of the snapshot.
+   pub events : Vec<Event>,
+}
+
+/// Client-side prediction on top of a [`Mirror`]. The [`Mirror`] keeps the authoritative game state, while the
+/// [`Predictor`] maintains a separate, predicted game state for the UI to display.
+///
+/// When the local player makes a move, [`predict`](Self::predict) snapshots the authoritative state and optimistically
+/// applies the events [`Predict::predict`] expects the server to yield. As authoritative events arrive, each one is
+/// compared against the next predicted event. Matching events are simply confirmed. On the first mismatch, the
+/// predicted state is rolled back to the snapshot and every authoritative event received since is re-applied.
+///
+/// Predicted events pass through any [`Middleware`](crate::Middleware) on the [`view`](Self::view) host, just like
+/// events supplied via [`Host::process_event`]. Authoritative events have already been through the middleware of the
+/// mirror's host, so they are applied to the view as they are.
+pub struct Predictor<Game, T> where
+   Game : Play,
+{
+   mirror : Mirror<Game, T>,
+   view : Host<Game>,
+   snapshot : Option<Game>,
+   predicted : VecDeque<Game::Event>,
+   confirmed : Vec<Game::Event>,
+   rollbacks : usize,
+   snapshots : u64,
+}
+
+impl<Game, T> Predictor<Game, T> where
+   Game : Predict + Wire,
+   Game::Event : Clone + PartialEq + Wire,
+   T : Transport,
+{
+   /// Wraps a [`Mirror`], starting with a predicted state identical to the authoritative one.
+   pub fn new(mirror : Mirror<Game, T>) -> Self {
+      let view = Host::new(mirror.host().clone_game());
+      let snapshots = mirror.snapshots();
+      Self {
+         mirror,
+         view,
+         snapshot: None,
+         predicted: VecDeque::new(),
+         confirmed: Vec::new(),
+         rollbacks: 0,
+         snapshots,
+      }
+   }
+
+   /// Returns the underlying [`Mirror`], which holds the authoritative game state.
+   pub fn mirror(&self) -> &Mirror<Game, T> {
+      &self.mirror
+   }
+
+   /// Mutably borrows the underlying [`Mirror`], e.g. to request a resync.
+   pub fn mirror_mut(&mut self) -> &mut Mirror<Game, T> {
+      &mut self.mirror
+   }
+
+   /// Returns the [`Host`] holding the predicted game state, which is what the UI should display.
+   pub fn view(&self) -> &Host<Game> {
+      &self.view
+   }
+
+   /// Returns `true` if there are predicted events that the server has not confirmed yet.
+   pub fn is_predicting(&self) -> bool {
+      !self.predicted.is_empty()
+   }
+
+   /// Returns the number of times a misprediction caused the predicted state to be rolled back.
+   pub fn rollbacks(&self) -> usize {
+      self.rollbacks
+   }
+
+   /// Optimistically applies the events expected to result from the local player submitting `input`, returning them
+   /// so that the UI can present them right away. The input itself still needs to be sent to the server separately.
+   pub fn predict(&mut self, input : &Game::Input) -> Vec<Game::Event> {
+      if self.snapshot.is_none() {
+         // Everything is confirmed, so the predicted state is the same as the authoritative one.
+         self.snapshot = Some(self.mirror.host().clone_game());
+         self.confirmed.clear();
+      }
+
+      let events = self.view.with_game(|game| game.predict(input));
+      let mut applied = Vec::with_capacity(events.len());
+      for mut event in events {
+         if self.view.process_event(&mut event) {
+            self.predicted.push_back(event.clone());
+            applied.push(event);
+         }
+      }
+
+      applied
+   }
+
+   /// Receives authoritative events via [`Mirror::poll`], and reconciles them with any outstanding predictions.
+   pub fn poll(&mut self) -> io::Result<Reconciliation<Game::Event>> {
+      let mut reconciliation = Reconciliation { rolled_back: false, events: Vec::new() };
+
+      let events = self.mirror.poll()?;
+
+      if self.snapshots != self.mirror.snapshots() {
+         self.snapshots = self.mirror.snapshots();
+         // The authoritative state was replaced by a snapshot, so start over from there.
+         *self.view.borrow_game_mut() = self.mirror.host().clone_game();
+         self.snapshot = None;
+         self.predicted.clear();
+         return Ok(Reconciliation { rolled_back: true, events });
+      }
+
+      for mut event in events {
+         if self.snapshot.is_none() {
+            // Nothing was predicted, so just keep up with the server.
+            self.view.apply_event(&mut event);
+            reconciliation.events.push(event);
+            continue;
+         }
+
+         self.confirmed.push(event.clone());
+         if self.predicted.front() == Some(&event) {
+            self.predicted.pop_front();
+         }
+         else {
+            self.roll_back(&mut reconciliation);
+         }
+
+         if self.predicted.is_empty() {
+            self.snapshot = None;
+         }
+      }
+
+      Ok(reconciliation)
+   }
+
+   /// Restores the predicted state to the snapshot taken before the first outstanding prediction, then re-applies the
+   /// authoritative events received since.
+   fn roll_back(&mut self, reconciliation : &mut Reconciliation<Game::Event>) {
+      if let Some(snapshot) = &self.snapshot {
+         *self.view.borrow_game_mut() = snapshot.clone();
+      }
+
+      reconciliation.rolled_back = true;
+      reconciliation.events.clear();
+      for event in self.confirmed.iter() {
+         let mut event = event.clone();
+         self.view.apply_event(&mut event);
+         reconciliation.events.push(event);
+      }
+
+      self.predicted.clear();
+      self.rollbacks += 1;
+   }
+}
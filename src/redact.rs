@@ -0,0 +1,28 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::Seat;
+
+/// Trait for values containing hidden information (e.g. a card dealt face-down) that should only be shown in full to
+/// some [`Seat`s](Seat). Typically implemented for a game's [`Event`](crate::Play::Event) type, and for the game state
+/// itself so that snapshots can be redacted too.
+///
+/// [`handle_event`](crate::Play::handle_event) always sees the full value. Redaction only applies to what is sent to
+/// each player, e.g. by a [`Broadcaster`](crate::replication::Broadcaster).
+pub trait Redact {
+   /// The redacted representation of the value.
+   type View;
+
+   /// Returns the view of this value that should be shown to `seat` (which may be [`Seat::SPECTATOR`]), or [`None`] if
+   /// the seat should not learn about it at all.
+   fn view_for(&self, seat : Seat) -> Option<Self::View>;
+}
+
+/// Produces a view of `value` for each of `seats`, skipping any seat that should not see it at all.
+pub fn views<'a, T, S>(value : &'a T, seats : S) -> impl Iterator<Item = (Seat, T::View)> + 'a where
+   T : Redact,
+   S : IntoIterator<Item = Seat> + 'a,
+{
+   seats.into_iter().filter_map(move |seat| value.view_for(seat).map(|view| (seat, view)))
+}
@@ -0,0 +1,446 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+//! Server-authoritative replication of a game over any [`Transport`].
+//!
+//! The server runs the game as usual, handing each [`Event`](Play::Event) yielded by [`play`](Play::play) to a
+//! [`Replicator`], which stamps it with a sequence number and sends it to a client. On the other end, a [`Mirror`]
+//! applies those events to its own [`Host`] via [`process_event`](Host::process_event) in sequence order, buffering any
+//! that arrive early. If the client falls too far behind, or its [checksum](Host::enable_checksums) disagrees with the
+//! server's, it asks the server for a snapshot of the full game state and resumes from there.
+//!
+//! For games with hidden information, a [`Broadcaster`] sends each [`Seat`] its own [redacted](Redact) stream of
+//! events instead, which a [`Mirror`] of the redacted game type can follow in exactly the same way.
+
+use std::{collections::BTreeMap, io};
+
+use crate::{
+   checksum::Desync,
+   net::{Transport, Wire, WireError},
+   redact::Redact,
+   Host, Play, Seat,
+};
+
+const EVENT : u8 = 0;
+const SNAPSHOT : u8 = 1;
+const RESYNC : u8 = 2;
+
+/// A decoded frame of the replication protocol.
+enum Packet<Event, Game> {
+   /// An event yielded by the server, along with the server's checksum after processing it (if enabled).
+   Event { seq : u64, checksum : Option<u64>, event : Event },
+
+   /// The full game state of the server, taken just before the event numbered `seq` was sent. The game state is
+   /// [`None`] if the client's seat is not allowed to see it at all (see [`Broadcaster`]).
+   Snapshot { seq : u64, game : Option<Game> },
+
+   /// Sent by a client to ask the server for a [`Packet::Snapshot`].
+   Resync,
+}
+
+impl<Event, Game> Packet<Event, Game> where
+   Event : Wire,
+   Game : Wire,
+{
+   fn encode_event(seq : u64, checksum : Option<u64>, event : &Event) -> Vec<u8> {
+      let mut out = vec![EVENT];
+      seq.encode(&mut out);
+      checksum.encode(&mut out);
+      event.encode(&mut out);
+      out
+   }
+
+   fn encode_snapshot(seq : u64, game : Option<&Game>) -> Vec<u8> {
+      let mut out = vec![SNAPSHOT];
+      seq.encode(&mut out);
+
+      // NOTE: Encoded just like an `Option<Game>`, without having to clone the game.
+      game.is_some().encode(&mut out);
+      if let Some(game) = game {
+         game.encode(&mut out);
+      }
+      out
+   }
+
+   fn decode(mut input : &[u8]) -> Result<Self, WireError> {
+      let packet = match u8::decode(&mut input)? {
+         EVENT => Self::Event {
+            seq: Wire::decode(&mut input)?,
+            checksum: Wire::decode(&mut input)?,
+            event: Wire::decode(&mut input)?,
+         },
+         SNAPSHOT => Self::Snapshot {
+            seq: Wire::decode(&mut input)?,
+            game: Wire::decode(&mut input)?,
+         },
+         RESYNC => Self::Resync,
+         _ => return Err(WireError),
+      };
+
+      if input.is_empty() { Ok(packet) } else { Err(WireError) }
+   }
+}
+
+/// Server side of a replicated game. Sends each [`Event`](Play::Event) it is given to a single client as part of a
+/// sequenced stream, and answers the client's requests for snapshots. Create one per connected client.
+pub struct Replicator<Game, T> where
+   Game : Play,
+{
+   host : Host<Game>,
+   transport : T,
+   next_seq : u64,
+}
+
+impl<Game, T> Replicator<Game, T> where
+   Game : Play + Wire,
+   Game::Event : Wire,
+   T : Transport,
+{
+   /// Creates a new [`Replicator`] that will send events from `host` over `transport`. If the game is already in
+   /// progress, call [`send_snapshot`](Self::send_snapshot) first so that the client starts from the right state.
+   pub fn new(host : Host<Game>, transport : T) -> Self {
+      Self { host, transport, next_seq: 0 }
+   }
+
+   /// Returns the [`Host`] whose events are being replicated.
+   pub fn host(&self) -> &Host<Game> {
+      &self.host
+   }
+
+   /// Returns the sequence number that will be assigned to the next published event.
+   pub fn next_seq(&self) -> u64 {
+      self.next_seq
+   }
+
+   /// Sends an [`Event`](Play::Event) that was just yielded by the host's coroutine to the client. If the host has
+   /// [checksums](Host::enable_checksums) enabled, the checksum of the game state as it is now (i.e. after the event
+   /// and its triggers were handled) is sent along with it. Returns the sequence number assigned to the event.
+   pub fn publish(&mut self, event : &Game::Event) -> io::Result<u64> {
+      let seq = self.next_seq;
+      let frame = Packet::<_, Game>::encode_event(seq, self.host.refresh_checksum(), event);
+      self.transport.send(&frame)?;
+      self.next_seq += 1;
+      Ok(seq)
+   }
+
+   /// Sends the full game state to the client, which will discard any events older than the snapshot.
+   pub fn send_snapshot(&mut self) -> io::Result<()> {
+      let frame = self.host.with_game(|game| Packet::<Game::Event, _>::encode_snapshot(self.next_seq, Some(&*game)));
+      self.transport.send(&frame)
+   }
+
+   /// Handles any requests received from the client. This should be called regularly.
+   pub fn poll(&mut self) -> io::Result<()> {
+      let mut needs_snapshot = false;
+      while let Some(frame) = self.transport.try_recv()? {
+         if let Packet::<Game::Event, Game>::Resync = Packet::decode(&frame)? {
+            needs_snapshot = true;
+         }
+      }
+
+      // NOTE: Several requests may have piled up, but one snapshot is enough to answer all of them.
+      if needs_snapshot {
+         self.send_snapshot()?;
+      }
+
+      Ok(())
+   }
+}
+
+/// A client that was unsubscribed from a [`Broadcaster`] because sending to it (or receiving from it) failed.
+#[derive(Debug)]
+pub struct Disconnected {
+   /// The seat the client was sitting in.
+   pub seat : Seat,
+
+   /// The error that caused the client to be unsubscribed.
+   pub error : io::Error,
+}
+
+/// A single client of a [`Broadcaster`].
+struct Subscriber<T> {
+   seat : Seat,
+   transport : T,
+   next_seq : u64,
+}
+
+/// Server side of a replicated game with hidden information. Like a [`Replicator`], except that it sends to any number
+/// of clients, each sitting in a particular [`Seat`] (or [`Seat::SPECTATOR`]), and each client only ever receives the
+/// [`Redact::view_for`] its seat.
+///
+/// Both the game's [`Event`s](Play::Event) and the game state itself are redacted, so that snapshots don't leak hidden
+/// information either. The views are expected to form a game of their own, i.e. clients should use a [`Mirror`] of a
+/// game whose [`Event`](Play::Event) type is the view of the server's event type. Since each seat sees different
+/// state, checksums are never sent.
+///
+/// A failure to reach one client never affects the others. Whenever a client's [`Transport`] fails (or it sends a
+/// malformed frame), the client is unsubscribed, and reported as [`Disconnected`] so that the server can clean up.
+pub struct Broadcaster<Game, T> where
+   Game : Play,
+{
+   host : Host<Game>,
+   subscribers : Vec<Subscriber<T>>,
+}
+
+impl<Game, T> Broadcaster<Game, T> where
+   Game : Play + Redact,
+   Game::View : Wire,
+   Game::Event : Redact,
+   <Game::Event as Redact>::View : Wire,
+   T : Transport,
+{
+   /// Creates a new [`Broadcaster`] for events from `host`, with no subscribers.
+   pub fn new(host : Host<Game>) -> Self {
+      Self { host, subscribers: Vec::new() }
+   }
+
+   /// Returns the [`Host`] whose events are being replicated.
+   pub fn host(&self) -> &Host<Game> {
+      &self.host
+   }
+
+   /// Adds a client sitting in `seat`, sending it a redacted snapshot of the current game state right away. If the
+   /// snapshot can't be sent, the client is **not** added.
+   pub fn subscribe(&mut self, seat : Seat, transport : T) -> io::Result<()> {
+      let mut subscriber = Subscriber { seat, transport, next_seq: 0 };
+      send_snapshot(&self.host, &mut subscriber)?;
+      self.subscribers.push(subscriber);
+      Ok(())
+   }
+
+   /// Returns the seat of every subscribed client, in the order they subscribed.
+   pub fn seats(&self) -> impl Iterator<Item = Seat> + '_ {
+      self.subscribers.iter().map(|subscriber| subscriber.seat)
+   }
+
+   /// Sends each subscriber its view of an [`Event`](Play::Event) that was just yielded by the host's coroutine.
+   /// Subscribers whose seat should not see the event at all are skipped. Returns every subscriber that could not be
+   /// reached, which are unsubscribed.
+   pub fn publish(&mut self, event : &Game::Event) -> Vec<Disconnected> {
+      self.retain(|_, subscriber| {
+         if let Some(view) = event.view_for(subscriber.seat) {
+            let frame = Packet::<_, Game::View>::encode_event(subscriber.next_seq, None, &view);
+            subscriber.transport.send(&frame)?;
+            subscriber.next_seq += 1;
+         }
+         Ok(())
+      })
+   }
+
+   /// Handles any requests received from subscribers. This should be called regularly. Returns every subscriber that
+   /// could not be reached (or sent a malformed frame), which are unsubscribed.
+   pub fn poll(&mut self) -> Vec<Disconnected> {
+      self.retain(|host, subscriber| {
+         let mut needs_snapshot = false;
+         while let Some(frame) = subscriber.transport.try_recv()? {
+            let packet = Packet::<<Game::Event as Redact>::View, Game::View>::decode(&frame)?;
+            if let Packet::Resync = packet {
+               needs_snapshot = true;
+            }
+         }
+
+         if needs_snapshot {
+            send_snapshot(host, subscriber)?;
+         }
+         Ok(())
+      })
+   }
+
+   /// Calls `f` for each subscriber, unsubscribing any for which it fails.
+   fn retain(&mut self, mut f : impl FnMut(&Host<Game>, &mut Subscriber<T>) -> io::Result<()>) -> Vec<Disconnected> {
+      let mut disconnected = Vec::new();
+      self.subscribers.retain_mut(|subscriber| match f(&self.host, subscriber) {
+         Ok(()) => true,
+         Err(error) => {
+            disconnected.push(Disconnected { seat: subscriber.seat, error });
+            false
+         },
+      });
+      disconnected
+   }
+}
+
+/// Sends a subscriber of a [`Broadcaster`] its view of the full game state. Seats that should not see the game state
+/// at all are sent a snapshot without one, so that they can still catch up with the stream of events.
+fn send_snapshot<Game, T>(host : &Host<Game>, subscriber : &mut Subscriber<T>) -> io::Result<()> where
+   Game : Play + Redact,
+   Game::View : Wire,
+   Game::Event : Redact,
+   <Game::Event as Redact>::View : Wire,
+   T : Transport,
+{
+   let view = host.with_game(|game| game.view_for(subscriber.seat));
+   let frame = Packet::<<Game::Event as Redact>::View, _>::encode_snapshot(subscriber.next_seq, view.as_ref());
+   subscriber.transport.send(&frame)
+}
+
+/// Client side of a replicated game. Applies [`Event`s](Play::Event) received from a [`Replicator`] to a local
+/// [`Host`] via [`process_event`](Host::process_event), strictly in sequence order.
+///
+/// Events that arrive early are buffered until the gap before them is filled. If too many events are buffered, or a
+/// [checksum](Host::enable_checksums) mismatch reveals that the local state has diverged, the [`Mirror`] asks the
+/// server for a snapshot and stops applying events until it arrives.
+pub struct Mirror<Game, T> where
+   Game : Play,
+{
+   host : Host<Game>,
+   transport : T,
+   next_seq : u64,
+   buffered : BTreeMap<u64, (Option<u64>, Game::Event)>,
+   max_buffered : usize,
+   is_resyncing : bool,
+   snapshots : u64,
+   last_desync : Option<Desync>,
+   error : Option<io::Error>,
+}
+
+impl<Game, T> Mirror<Game, T> where
+   Game : Play + Wire,
+   Game::Event : Wire,
+   T : Transport,
+{
+   /// The default value for [`set_max_buffered`](Self::set_max_buffered).
+   pub const DEFAULT_MAX_BUFFERED : usize = 64;
+
+   /// Creates a new [`Mirror`] that applies events received over `transport` to `host`. The game state of `host` is
+   /// expected to match the server's initial state (or be replaced by a snapshot before any events are applied).
+   pub fn new(host : Host<Game>, transport : T) -> Self {
+      Self {
+         host,
+         transport,
+         next_seq: 0,
+         buffered: BTreeMap::new(),
+         max_buffered: Self::DEFAULT_MAX_BUFFERED,
+         is_resyncing: false,
+         snapshots: 0,
+         last_desync: None,
+         error: None,
+      }
+   }
+
+   /// Returns the [`Host`] that events are being applied to.
+   pub fn host(&self) -> &Host<Game> {
+      &self.host
+   }
+
+   /// Returns the sequence number of the next event to be applied.
+   pub fn next_seq(&self) -> u64 {
+      self.next_seq
+   }
+
+   /// Returns `true` if the [`Mirror`] is waiting for a snapshot from the server.
+   pub fn is_resyncing(&self) -> bool {
+      self.is_resyncing
+   }
+
+   /// Returns the number of snapshots that have replaced the game state so far, whether they were requested via
+   /// [`request_resync`](Self::request_resync) or sent by the server unprompted.
+   pub fn snapshots(&self) -> u64 {
+      self.snapshots
+   }
+
+   /// Returns the most recent [`Desync`] detected by comparing checksums, if any.
+   pub fn last_desync(&self) -> Option<Desync> {
+      self.last_desync
+   }
+
+   /// Sets how many out-of-order events may be buffered while waiting for a missing event before giving up and
+   /// asking for a snapshot. Defaults to [`DEFAULT_MAX_BUFFERED`](Self::DEFAULT_MAX_BUFFERED).
+   pub fn set_max_buffered(&mut self, max_buffered : usize) {
+      self.max_buffered = max_buffered;
+   }
+
+   /// Asks the server for a snapshot of the full game state. No more events will be applied until it arrives.
+   pub fn request_resync(&mut self) -> io::Result<()> {
+      self.is_resyncing = true;
+      self.transport.send(&[RESYNC])
+   }
+
+   /// Receives everything that is available from the server, applying as many events as possible. Returns each event
+   /// that was applied (after being modified by any [`Middleware`](crate::Middleware)), in order, so that the UI can
+   /// present them. If anything goes wrong after some events were applied (e.g. the transport fails, or a frame can't
+   /// be decoded), those events are returned and the error is reported by the next call instead.
+   pub fn poll(&mut self) -> io::Result<Vec<Game::Event>> {
+      if let Some(err) = self.error.take() {
+         return Err(err);
+      }
+
+      let mut applied = Vec::new();
+      match self.receive(&mut applied) {
+         Err(err) if applied.is_empty() => Err(err),
+         result => {
+            // NOTE: Report any events that were applied before the error, since they can't be applied again.
+            self.error = result.err();
+            Ok(applied)
+         },
+      }
+   }
+
+   /// Receives and applies frames until none are left, adding each applied event to `applied`.
+   fn receive(&mut self, applied : &mut Vec<Game::Event>) -> io::Result<()> {
+      while let Some(frame) = self.transport.try_recv()? {
+         match Packet::decode(&frame)? {
+            Packet::Event { seq, checksum, event } => {
+               // Ignore duplicates of events that have already been applied.
+               if seq >= self.next_seq {
+                  self.buffered.insert(seq, (checksum, event));
+               }
+            },
+            Packet::Snapshot { seq, game } => {
+               // NOTE: The snapshot was taken after `seq` events were applied, so the host's event count and checksum
+               // must match that, or the next desync would be reported against the wrong state.
+               self.host.restore(game, seq);
+               self.snapshots += 1;
+               self.next_seq = seq;
+               self.buffered = self.buffered.split_off(&seq);
+               self.is_resyncing = false;
+            },
+            Packet::Resync => { },
+         }
+
+         self.apply_buffered(applied)?;
+
+         if self.buffered.len() > self.max_buffered {
+            if self.is_resyncing {
+               // The snapshot is bound to be newer than the oldest buffered events.
+               self.buffered.pop_first();
+            }
+            else {
+               self.request_resync()?;
+            }
+         }
+      }
+
+      Ok(())
+   }
+
+   /// Applies buffered events for as long as they form a contiguous sequence.
+   fn apply_buffered(&mut self, applied : &mut Vec<Game::Event>) -> io::Result<()> {
+      while !self.is_resyncing {
+         let Some(entry) = self.buffered.first_entry() else { break };
+         if *entry.key() != self.next_seq {
+            break;
+         }
+
+         let (expected, mut event) = entry.remove();
+         let index = self.next_seq;
+         self.next_seq += 1;
+
+         if !self.host.process_event(&mut event) {
+            continue;
+         }
+
+         if let (Some(expected), Some(actual)) = (expected, self.host.checksum()) {
+            if expected != actual {
+               self.last_desync = Some(Desync { index, expected, actual });
+               self.request_resync()?;
+            }
+         }
+
+         applied.push(event);
+      }
+
+      Ok(())
+   }
+}
@@ -0,0 +1,56 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+/// A small, fast, seedable pseudo-random number generator (SplitMix64), used wherever posturn needs randomness, e.g.
+/// for [MCTS](crate::mcts) rollouts. The same seed always produces the same sequence of numbers on every platform,
+/// which keeps simulations reproducible.
+///
+/// This is **not** suitable for cryptography, or for shuffling a deck in a game played for real stakes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rng {
+   state : u64,
+}
+
+impl Rng {
+   /// Creates a new [`Rng`] from a `seed`.
+   pub fn new(seed : u64) -> Self {
+      Self { state: seed }
+   }
+
+   /// Returns the next pseudo-random `u64`.
+   pub fn next_u64(&mut self) -> u64 {
+      self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+      let mut z = self.state;
+      z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+      z ^ (z >> 31)
+   }
+
+   /// Returns a pseudo-random number in `0..bound`.
+   ///
+   /// # Safety
+   /// This function will panic if `bound` is zero.
+   ///
+   pub fn below(&mut self, bound : usize) -> usize {
+      assert!(bound > 0, "cannot pick a number below zero");
+      ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+   }
+
+   /// Returns a pseudo-random `f64` in `0.0..1.0`.
+   pub fn unit(&mut self) -> f64 {
+      (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+   }
+
+   /// Picks one of `items` at random, or returns [`None`] if there are none.
+   pub fn pick<'a, T>(&mut self, items : &'a [T]) -> Option<&'a T> {
+      if items.is_empty() { None } else { items.get(self.below(items.len())) }
+   }
+
+   /// Shuffles `items` in place.
+   pub fn shuffle<T>(&mut self, items : &mut [T]) {
+      for index in (1..items.len()).rev() {
+         items.swap(index, self.below(index + 1));
+      }
+   }
+}
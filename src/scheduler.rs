@@ -0,0 +1,234 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::{session::SessionError, Play, Seat, Seated, Session};
+
+/// Identifies a [`Session`] owned by a [`Scheduler`]. Once a session is retired or removed, its id is never valid
+/// again, even though its slot may be reused by a new session.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SessionId {
+   index : u32,
+   generation : u32,
+}
+
+/// Returned when a [`Scheduler`] can't route an [`Input`](Play::Input).
+#[derive(Debug, Eq, PartialEq)]
+pub enum SchedulerError {
+   /// No session with this id is owned by the scheduler, e.g. because it was already retired.
+   UnknownSession(SessionId),
+
+   /// The session rejected the input.
+   Session(SessionError),
+}
+
+impl From<SessionError> for SchedulerError {
+   fn from(err : SessionError) -> Self {
+      Self::Session(err)
+   }
+}
+
+/// A finished [`Session`], as [retired](Scheduler::retire) by a [`Scheduler`].
+pub struct Retired<Game> where
+   Game : Play,
+{
+   /// The id the session had.
+   pub id : SessionId,
+
+   /// How the game ended.
+   pub outcome : Game::Outcome,
+
+   /// Any events still in the session's outbox, which were never [flushed](Scheduler::flush).
+   pub unsent : Vec<Game::Event>,
+}
+
+/// A slot in a [`Scheduler`], which may or may not hold a session.
+struct Slot<Game> where
+   Game : Play + 'static,
+{
+   generation : u32,
+   entry : Option<Entry<Game>>,
+}
+
+/// A session owned by a [`Scheduler`], along with the events it has yielded but which have not been sent yet.
+struct Entry<Game> where
+   Game : Play + 'static,
+{
+   session : Session<Game>,
+   outbox : Vec<Game::Event>,
+   is_ready : bool,
+}
+
+/// Owns many [`Session`s](Session) at once, e.g. on a server where each game spends most of its time waiting for
+/// input. All on a single thread, the scheduler:
+///
+/// 1. Routes each incoming `(SessionId, Seat, Input)` message to the right session via [`route`](Self::route).
+/// 2. Collects each event a session yields into that session's outbox, to be sent via [`flush`](Self::flush).
+/// 3. Hands back finished sessions via [`retire`](Self::retire), freeing their slots for new sessions.
+///
+/// Sessions are stored in a slab, and outboxes keep their capacity after being flushed, so a busy scheduler allocates
+/// very little once it has warmed up. Every event a session yields is collected (see [`Session::log_events`]),
+/// including prompts answered by [`Agent`s](crate::Agent) bound to the session, so that clients see the moves made by
+/// bots too.
+pub struct Scheduler<Game> where
+   Game : Play + 'static,
+{
+   slots : Vec<Slot<Game>>,
+   free : Vec<u32>,
+   ready : Vec<SessionId>,
+   finished : Vec<SessionId>,
+   len : usize,
+}
+
+impl<Game> Default for Scheduler<Game> where
+   Game : Play + 'static,
+{
+   fn default() -> Self {
+      Self { slots: Vec::new(), free: Vec::new(), ready: Vec::new(), finished: Vec::new(), len: 0 }
+   }
+}
+
+impl<Game> Scheduler<Game> where
+   Game : Seated + 'static,
+   Game::Event : Clone,
+{
+   /// Creates a new, empty [`Scheduler`].
+   pub fn new() -> Self {
+      Self::default()
+   }
+
+   /// Creates a new, empty [`Scheduler`] with room for `capacity` sessions before it needs to allocate.
+   pub fn with_capacity(capacity : usize) -> Self {
+      Self {
+         slots: Vec::with_capacity(capacity),
+         free: Vec::with_capacity(capacity),
+         ready: Vec::with_capacity(capacity),
+         finished: Vec::with_capacity(capacity),
+         len: 0,
+      }
+   }
+
+   /// Returns the number of sessions owned by the scheduler, including finished sessions that have not been retired.
+   pub fn len(&self) -> usize {
+      self.len
+   }
+
+   /// Returns `true` if the scheduler owns no sessions.
+   pub fn is_empty(&self) -> bool {
+      self.len == 0
+   }
+
+   /// Takes ownership of `session`, returning its new id. The session's pending event (if any) is added to its outbox,
+   /// followed by every event it yields from then on.
+   pub fn insert(&mut self, mut session : Session<Game>) -> SessionId {
+      let index = self.free.pop().unwrap_or_else(|| {
+         self.slots.push(Slot { generation: 0, entry: None });
+         (self.slots.len() - 1) as u32
+      });
+
+      // Only the pending event is sent for anything that happened before the session was handed over.
+      session.take_events().for_each(drop);
+      session.log_events();
+      let outbox = session.pending().cloned().into_iter().collect();
+
+      let slot = &mut self.slots[index as usize];
+      slot.entry = Some(Entry { session, outbox, is_ready: false });
+      self.len += 1;
+
+      let id = SessionId { index, generation: slot.generation };
+      self.collect(id);
+      id
+   }
+
+   /// Returns the session with the given `id`, if the scheduler still owns it.
+   pub fn get(&self, id : SessionId) -> Option<&Session<Game>> {
+      let slot = self.slots.get(id.index as usize).filter(|slot| slot.generation == id.generation)?;
+      slot.entry.as_ref().map(|entry| &entry.session)
+   }
+
+   /// Resumes the session with the given `id` with an `input` from `seat` (see [`Session::resume_as`]), and adds every
+   /// event it yields to its outbox.
+   pub fn route(&mut self, id : SessionId, seat : Seat, input : Game::Input) -> Result<(), SchedulerError> {
+      let entry = entry_mut(&mut self.slots, id).ok_or(SchedulerError::UnknownSession(id))?;
+      entry.session.resume_as(seat, input)?;
+      self.collect(id);
+      Ok(())
+   }
+
+   /// Removes and returns the events in the outbox of the session with the given `id`, if the scheduler still owns it.
+   pub fn drain_outbox(&mut self, id : SessionId) -> Option<std::vec::Drain<'_, Game::Event>> {
+      entry_mut(&mut self.slots, id).map(|entry| entry.outbox.drain(..))
+   }
+
+   /// Passes every event in every outbox to `send`, in the order the events were yielded, and empties the outboxes.
+   pub fn flush(&mut self, mut send : impl FnMut(SessionId, Game::Event)) {
+      for id in self.ready.drain(..) {
+         let Some(entry) = entry_mut(&mut self.slots, id) else {
+            continue;
+         };
+
+         entry.is_ready = false;
+         for event in entry.outbox.drain(..) {
+            send(id, event);
+         }
+      }
+   }
+
+   /// Passes every finished session to `retire`, freeing their slots for new sessions.
+   pub fn retire(&mut self, mut retire : impl FnMut(Retired<Game>)) {
+      // Hand the list back afterwards, to keep its capacity.
+      let mut finished = std::mem::take(&mut self.finished);
+      for id in finished.drain(..) {
+         if let Some(entry) = self.take(id) {
+            let outcome = entry.session.into_outcome().expect("only finished sessions are retired");
+            retire(Retired { id, outcome, unsent: entry.outbox });
+         }
+      }
+      self.finished = finished;
+   }
+
+   /// Removes the session with the given `id`, whether or not it is finished, e.g. to abandon a game. Any events in its
+   /// outbox are dropped.
+   pub fn remove(&mut self, id : SessionId) -> Option<Session<Game>> {
+      self.take(id).map(|entry| entry.session)
+   }
+
+   /// Takes the entry with the given `id` out of its slot, freeing the slot. A slot whose generation has run out is
+   /// never reused, since a new session in it could be mistaken for one with an old id.
+   fn take(&mut self, id : SessionId) -> Option<Entry<Game>> {
+      let slot = self.slots.get_mut(id.index as usize).filter(|slot| slot.generation == id.generation)?;
+      let entry = slot.entry.take()?;
+      if let Some(generation) = slot.generation.checked_add(1) {
+         slot.generation = generation;
+         self.free.push(id.index);
+      }
+      self.len -= 1;
+      Some(entry)
+   }
+
+   /// Moves the events yielded by the session with the given `id` to its outbox, and marks it as finished once the game
+   /// is over.
+   fn collect(&mut self, id : SessionId) {
+      let Some(entry) = entry_mut(&mut self.slots, id) else {
+         return;
+      };
+
+      entry.outbox.extend(entry.session.take_events());
+      if entry.session.is_complete() {
+         self.finished.push(id);
+      }
+
+      if !entry.is_ready && !entry.outbox.is_empty() {
+         entry.is_ready = true;
+         self.ready.push(id);
+      }
+   }
+}
+
+/// Returns the entry with the given `id`, if its slot still holds it.
+fn entry_mut<Game>(slots : &mut [Slot<Game>], id : SessionId) -> Option<&mut Entry<Game>> where
+   Game : Play + 'static,
+{
+   let slot = slots.get_mut(id.index as usize).filter(|slot| slot.generation == id.generation)?;
+   slot.entry.as_mut()
+}
@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::{Agent, Host, LegalMoves, Play, Seat, Seated, Session};
+
+/// The best [`Input`](Play::Input) found by a search, along with its score.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchResult<Input> {
+   /// The input to play.
+   pub input : Input,
+
+   /// The score of the position reached by playing [`input`](Self::input), assuming best play by everyone afterwards.
+   pub score : i32,
+
+   /// The number of positions visited during the search.
+   pub nodes : usize,
+}
+
+/// Searches up to `depth` moves ahead of the prompt pending in `root` for the best [`Input`](Play::Input) for `seat`,
+/// using minimax with alpha-beta pruning. Returns [`None`] if the game is over or there are no legal inputs.
+///
+/// A game coroutine can't be copied, so each position is reached by starting a fresh [`Host`] from a copy of the game
+/// state and resuming it. This means that the game's [`play`](Play::play) coroutine **must** pick up where it left
+/// off when started from any state it yields, e.g. by keeping track of whose turn it is in the game state rather than
+/// in a local variable.
+///
+/// Positions at the depth limit (or where the game is over) are scored by `eval`, from the point of view of the given
+/// seat. Prompts that `seat` may answer maximize the score, while all other prompts minimize it. Prompts that may be
+/// answered by any seat are treated as `seat`'s own.
+///
+/// Simultaneous prompts (see [`Context::ask_all`](crate::Context::ask_all)) cannot be searched through, and are scored
+/// as if they were at the depth limit.
+pub fn minimax<Game, Eval>(root : &Session<Game>, seat : Seat, depth : usize, mut eval : Eval)
+   -> Option<SearchResult<Game::Input>> where
+   Game : LegalMoves + Seated + Clone + 'static,
+   Game::Input : Clone,
+   Eval : FnMut(&Session<Game>, Seat) -> i32,
+{
+   let mut search = Search { seat, eval: &mut eval, nodes: 0 };
+   let mut best : Option<SearchResult<Game::Input>> = None;
+   let mut alpha = i32::MIN;
+
+   for input in root.legal_inputs() {
+      let Some(child) = search.child(root, input.clone()) else {
+         continue;
+      };
+
+      let score = search.alpha_beta(&child, depth.saturating_sub(1), alpha, i32::MAX);
+      if best.as_ref().map_or(true, |best| score > best.score) {
+         alpha = alpha.max(score);
+         best = Some(SearchResult { input, score, nodes: 0 });
+      }
+   }
+
+   best.map(|best| SearchResult { nodes: search.nodes, ..best })
+}
+
+/// State shared by every node of a [`minimax`] search.
+struct Search<'a, Eval> {
+   seat : Seat,
+   eval : &'a mut Eval,
+   nodes : usize,
+}
+
+impl<Eval> Search<'_, Eval> {
+   /// Starts a copy of `node` in a fresh [`Host`] and resumes it with `input`. Returns [`None`] if the copy rejects
+   /// the input.
+   fn child<Game>(&mut self, node : &Session<Game>, input : Game::Input) -> Option<Session<Game>> where
+      Game : Play + Clone + 'static,
+   {
+      let mut child = Session::new(Host::new(node.host().clone_game())).ok()?;
+      self.nodes += 1;
+      child.resume(input).ok()?;
+      Some(child)
+   }
+
+   /// Scores `node` by searching up to `depth` moves ahead, pruning any moves that cannot affect the result.
+   fn alpha_beta<Game>(&mut self, node : &Session<Game>, depth : usize, mut alpha : i32, mut beta : i32) -> i32 where
+      Game : LegalMoves + Seated + Clone + 'static,
+      Game::Input : Clone,
+      Eval : FnMut(&Session<Game>, Seat) -> i32,
+   {
+      let moves = node.legal_inputs();
+      if depth == 0 || node.is_complete() || moves.is_empty() {
+         return (self.eval)(node, self.seat);
+      }
+
+      let answering = node.seats_to_answer();
+      let is_maximizing = answering.is_empty() || answering.contains(self.seat);
+      let mut best = if is_maximizing { i32::MIN } else { i32::MAX };
+      let mut searched = false;
+
+      for input in moves {
+         let Some(child) = self.child(node, input) else {
+            continue;
+         };
+
+         searched = true;
+         let score = self.alpha_beta(&child, depth - 1, alpha, beta);
+         if is_maximizing {
+            best = best.max(score);
+            alpha = alpha.max(best);
+         }
+         else {
+            best = best.min(score);
+            beta = beta.min(best);
+         }
+
+         if alpha >= beta {
+            break;
+         }
+      }
+
+      // If none of the moves could be played (e.g. at a simultaneous prompt), treat this as a leaf.
+      if searched { best } else { (self.eval)(node, self.seat) }
+   }
+}
+
+/// An [`Agent`] that picks moves by [`minimax`] search, starting from a copy of the game state it is shown.
+pub struct MinimaxAgent<Eval> {
+   depth : usize,
+   eval : Eval,
+}
+
+impl<Eval> MinimaxAgent<Eval> {
+   /// Creates a new [`MinimaxAgent`] searching up to `depth` moves ahead, scoring positions with `eval` (see
+   /// [`minimax`]).
+   pub fn new(depth : usize, eval : Eval) -> Self {
+      Self { depth, eval }
+   }
+}
+
+impl<Game, Eval> Agent<Game> for MinimaxAgent<Eval> where
+   Game : LegalMoves + Seated + Clone + 'static,
+   Game::Input : Clone,
+   Eval : FnMut(&Session<Game>, Seat) -> i32,
+{
+   fn choose(&mut self, game : &Game, prompt : &Game::Event) -> Game::Input {
+      let seat = game.seats_to_answer(prompt).first().expect("agents only answer prompts meant for their seat");
+      let root = Session::new(Host::new(game.clone())).expect("a new host can always be played");
+      minimax(&root, seat, self.depth, &mut self.eval).expect("a pending prompt has at least one legal input").input
+   }
+}
@@ -0,0 +1,143 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::{net::{Wire, WireError}, Play};
+
+/// Identifies one of the players taking part in a game, e.g. for deciding whose input is authoritative on a given
+/// turn. Seats are numbered from `0` in whatever order makes sense for the game.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Seat(pub u8);
+
+impl Seat {
+   /// A pseudo-seat for observers who are not taking part in the game. Anything shown to this seat (e.g. via
+   /// [`Redact`](crate::redact::Redact)) should be public information.
+   pub const SPECTATOR : Seat = Seat(u8::MAX);
+}
+
+impl std::fmt::Display for Seat {
+   fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      if *self == Self::SPECTATOR { f.write_str("spectator") } else { write!(f, "seat {}", self.0) }
+   }
+}
+
+impl Wire for Seat {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.0.encode(out)
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      u8::decode(input).map(Self)
+   }
+}
+
+/// A set of [`Seat`s](Seat), e.g. the seats that must answer a prompt. Implemented as a bit set, so only seats
+/// numbered below [`Seats::CAPACITY`] can be members.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct Seats(u64);
+
+impl Seats {
+   /// The number of seats a [`Seats`] set can hold. Seats numbered from here onwards (including
+   /// [`Seat::SPECTATOR`]) are never members.
+   pub const CAPACITY : u8 = 64;
+
+   /// The empty set.
+   pub const EMPTY : Seats = Seats(0);
+
+   /// Returns the set containing every seat numbered below `count`.
+   pub fn first_n(count : u8) -> Self {
+      assert!(count <= Self::CAPACITY, "a set of seats can only hold {} seats", Self::CAPACITY);
+      Self(if count == Self::CAPACITY { u64::MAX } else { (1 << count) - 1 })
+   }
+
+   /// Adds `seat` to the set, returning `true` if it wasn't already a member.
+   /// 
+   /// # Safety
+   /// This function will panic if `seat` is numbered [`CAPACITY`](Self::CAPACITY) or higher.
+   /// 
+   pub fn insert(&mut self, seat : Seat) -> bool {
+      assert!(seat.0 < Self::CAPACITY, "{seat} cannot be added to a set of seats");
+      let is_new = !self.contains(seat);
+      self.0 |= 1 << seat.0;
+      is_new
+   }
+
+   /// Removes `seat` from the set, returning `true` if it was a member.
+   pub fn remove(&mut self, seat : Seat) -> bool {
+      let was_member = self.contains(seat);
+      if was_member {
+         self.0 &= !(1 << seat.0);
+      }
+      was_member
+   }
+
+   /// Returns `true` if `seat` is a member of the set.
+   pub fn contains(&self, seat : Seat) -> bool {
+      seat.0 < Self::CAPACITY && self.0 & (1 << seat.0) != 0
+   }
+
+   /// Returns the number of seats in the set.
+   pub fn len(&self) -> usize {
+      self.0.count_ones() as usize
+   }
+
+   /// Returns `true` if the set is empty.
+   pub fn is_empty(&self) -> bool {
+      self.0 == 0
+   }
+
+   /// Returns the lowest-numbered seat in the set, if any.
+   pub fn first(&self) -> Option<Seat> {
+      self.iter().next()
+   }
+
+   /// Iterates over the seats in the set, in ascending order.
+   pub fn iter(&self) -> impl Iterator<Item = Seat> {
+      let bits = self.0;
+      (0..Self::CAPACITY).filter(move |index| bits & (1 << index) != 0).map(Seat)
+   }
+}
+
+impl std::fmt::Debug for Seats {
+   fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.debug_set().entries(self.iter()).finish()
+   }
+}
+
+impl From<Seat> for Seats {
+   fn from(seat : Seat) -> Self {
+      let mut seats = Self::EMPTY;
+      seats.insert(seat);
+      seats
+   }
+}
+
+impl FromIterator<Seat> for Seats {
+   fn from_iter<I : IntoIterator<Item = Seat>>(iter : I) -> Self {
+      let mut seats = Self::EMPTY;
+      for seat in iter {
+         seats.insert(seat);
+      }
+      seats
+   }
+}
+
+impl Wire for Seats {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.0.encode(out)
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      u64::decode(input).map(Self)
+   }
+}
+
+/// Trait for games played by more than one player, where each prompt (i.e. each [`Event`](Play::Event) yielded by
+/// [`play`](Play::play)) must be answered by particular [`Seat`s](Seat). A [`Session`](crate::Session) uses this to
+/// reject [`Input`s](Play::Input) submitted from the wrong seat via
+/// [`resume_as`](crate::Session::resume_as), instead of feeding them to the game.
+pub trait Seated : Play {
+   /// Returns the seats allowed to answer `prompt`. An empty set means the prompt is purely informational, and may be
+   /// answered from any seat.
+   fn seats_to_answer(&self, prompt : &Self::Event) -> Seats;
+}
@@ -0,0 +1,500 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{collections::{BTreeMap, HashMap}, pin::Pin};
+
+use genawaiter::{Coroutine, GeneratorState};
+
+use crate::{host::PlayError, Agent, Host, LegalMoves, Play, Resume, Seat, Seated, Seats};
+
+/// The type-erased coroutine driving a [`Session`].
+type GameCoroutine<Game> = Pin<Box<dyn Coroutine<
+   Yield = <Game as Play>::Event,
+   Resume = Resume<<Game as Play>::Input>,
+   Return = <Game as Play>::Outcome>>>;
+
+/// Checks whether an [`Input`](Play::Input) is legal in response to a prompt. See [`Session::validate_inputs`].
+type LegalityCheck<Game> = fn(&Game, &<Game as Play>::Event, &<Game as Play>::Input) -> bool;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionError {
+   /// The game is over, and cannot be resumed any further.
+   Complete,
+
+   /// An [`Input`](Play::Input) was submitted from a [`Seat`] that is not allowed to answer the pending prompt.
+   WrongSeat {
+      /// The seat the input was submitted from.
+      seat : Seat,
+
+      /// The seats allowed to answer the pending prompt.
+      expected : Seats,
+   },
+
+   /// A [`Seat`] tried to answer a simultaneous prompt (see [`Context::ask_all`](crate::Context::ask_all)) more than
+   /// once.
+   AlreadySubmitted {
+      /// The seat the input was submitted from.
+      seat : Seat,
+   },
+
+   /// The pending prompt must be answered by particular seats (or by several seats at once), so inputs must be
+   /// submitted via [`Session::resume_as`]. See [`Session::require_seats`].
+   SeatRequired,
+
+   /// The submitted input is not one of the [`LegalMoves`] for the pending prompt. See
+   /// [`Session::validate_inputs`].
+   IllegalInput,
+}
+
+/// Owns a running game, keeping track of the [`Event`](Play::Event) the game is currently waiting on (or the final
+/// [`Outcome`](Play::Outcome), once the game is over).
+///
+/// Unlike the coroutine returned by [`Host::play`], a [`Session`] has a nameable type, starts the game as soon as it is
+/// created, and never needs to be resumed with a throwaway [`Input`](Play::Input).
+pub struct Session<Game> where
+   Game : Play + 'static,
+{
+   host : Host<Game>,
+   co : GameCoroutine<Game>,
+   state : GeneratorState<Game::Event, Game::Outcome>,
+   asked : Option<Asked<Game::Input>>,
+   agents : BTreeMap<Seat, Box<dyn Agent<Game>>>,
+   seats_of : Option<fn(&Game, &Game::Event) -> Seats>,
+   required_seats : Option<fn(&Game, &Game::Event) -> Seats>,
+   is_legal : Option<LegalityCheck<Game>>,
+   history : Option<History<Game>>,
+   log : Option<EventLog<Game::Event>>,
+}
+
+/// Everything needed to replay a recorded session from the start. See [`Session::recorded`].
+struct History<Game> where
+   Game : Play,
+{
+   initial : Game,
+   resumes : Vec<Resume<Game::Input>>,
+   clone_game : fn(&Game) -> Game,
+   clone_resume : fn(&Resume<Game::Input>) -> Resume<Game::Input>,
+}
+
+/// Copies of the events yielded since they were last taken. See [`Session::log_events`].
+struct EventLog<Event> {
+   events : Vec<Event>,
+   clone_event : fn(&Event) -> Event,
+}
+
+/// Inputs collected so far for a simultaneous prompt.
+struct Asked<Input> {
+   waiting : Seats,
+   inputs : HashMap<Seat, Input>,
+}
+
+impl<Game> Session<Game> where
+   Game : Play + 'static,
+{
+   /// Starts a new game on `host`, running it until the first [`Event`](Play::Event) is yielded (or the game ends). If
+   /// the game has already been started or cannot be updated, returns a [`PlayError`].
+   pub fn new(host : Host<Game>) -> Result<Self, PlayError> {
+      let mut co : GameCoroutine<Game> = Box::pin(host.start()?);
+      let state = co.as_mut().resume_with(Resume::Start);
+      let asked = Asked::take(&host);
+      Ok(Self {
+         host,
+         co,
+         state,
+         asked,
+         agents: BTreeMap::new(),
+         seats_of: None,
+         required_seats: None,
+         is_legal: None,
+         history: None,
+         log: None,
+      })
+   }
+
+   /// Like [`new`](Self::new), except that the session keeps a copy of the initial game state, and records every
+   /// [`Input`](Play::Input) the game is resumed with. This allows the session to be [forked](Self::fork), e.g. to
+   /// preview a "what-if" move without disturbing the game in progress.
+   pub fn recorded(host : Host<Game>) -> Result<Self, PlayError> where
+      Game : Clone,
+      Game::Input : Clone,
+   {
+      let initial = host.clone_game();
+      let mut session = Self::new(host)?;
+      session.history = Some(History {
+         initial,
+         resumes: Vec::new(),
+         clone_game: Game::clone,
+         clone_resume: Resume::clone,
+      });
+      Ok(session)
+   }
+
+   /// Returns the [`Host`] that manages the game state.
+   pub fn host(&self) -> &Host<Game> {
+      &self.host
+   }
+
+   /// Returns the [`Event`](Play::Event) the game is waiting on, or the [`Outcome`](Play::Outcome) if it is over.
+   pub fn state(&self) -> GeneratorState<&Game::Event, &Game::Outcome> {
+      match &self.state {
+         GeneratorState::Yielded(event) => GeneratorState::Yielded(event),
+         GeneratorState::Complete(outcome) => GeneratorState::Complete(outcome),
+      }
+   }
+
+   /// Returns the [`Event`](Play::Event) the game is waiting on, or [`None`] if the game is over.
+   pub fn pending(&self) -> Option<&Game::Event> {
+      match &self.state {
+         GeneratorState::Yielded(event) => Some(event),
+         GeneratorState::Complete(_) => None,
+      }
+   }
+
+   /// Returns the [`Outcome`](Play::Outcome) of the game, or [`None`] if the game is still in progress.
+   pub fn outcome(&self) -> Option<&Game::Outcome> {
+      match &self.state {
+         GeneratorState::Yielded(_) => None,
+         GeneratorState::Complete(outcome) => Some(outcome),
+      }
+   }
+
+   /// Returns `true` once the game is over.
+   pub fn is_complete(&self) -> bool {
+      matches!(self.state, GeneratorState::Complete(_))
+   }
+
+   /// Resumes the game with an [`Input`](Play::Input) for the pending [`Event`](Play::Event), running it until the
+   /// next event is yielded (or the game ends). Returns [`SessionError::Complete`] if the game is already over.
+   ///
+   /// The input isn't attributed to any seat, so every prompt is treated as informational, unless seats are required
+   /// via [`require_seats`](Self::require_seats). Prompts raised via [`Context::ask_all`](crate::Context::ask_all)
+   /// always need to know which seat each input came from, and are rejected with [`SessionError::SeatRequired`].
+   pub fn resume(&mut self, input : Game::Input) -> Result<GeneratorState<&Game::Event, &Game::Outcome>, SessionError> {
+      if self.is_complete() {
+         return Err(SessionError::Complete);
+      }
+
+      let is_seated = match (self.required_seats, self.pending()) {
+         (Some(required_seats), Some(prompt)) => !self.host.with_game(|game| required_seats(&game, prompt)).is_empty(),
+         _ => false,
+      };
+      if self.asked.is_some() || is_seated {
+         return Err(SessionError::SeatRequired);
+      }
+
+      self.answer(input)
+   }
+
+   /// Returns the [`Seats`] allowed to answer the pending prompt, as declared by [`Seated::seats_to_answer`]. If the
+   /// prompt was raised via [`Context::ask_all`](crate::Context::ask_all), returns the seats that have not submitted
+   /// an input yet instead. Returns an empty set if the game is over.
+   pub fn seats_to_answer(&self) -> Seats where
+      Game : Seated,
+   {
+      if let Some(asked) = &self.asked {
+         return asked.waiting;
+      }
+
+      match self.pending() {
+         Some(prompt) => self.host.with_game(|game| game.seats_to_answer(prompt)),
+         None => Seats::EMPTY,
+      }
+   }
+
+   /// Resumes the game with an [`Input`](Play::Input) submitted from `seat`, like [`resume`](Self::resume). If the
+   /// pending prompt must be answered by other seats, the input is **not** passed to the game, and
+   /// [`SessionError::WrongSeat`] is returned instead.
+   ///
+   /// If the prompt was raised via [`Context::ask_all`](crate::Context::ask_all), the input is held back until every
+   /// asked seat has submitted one, and only then is the game resumed. Until that happens, the same prompt stays
+   /// pending.
+   pub fn resume_as(&mut self, seat : Seat, input : Game::Input)
+      -> Result<GeneratorState<&Game::Event, &Game::Outcome>, SessionError> where
+      Game : Seated,
+   {
+      if self.asked.is_some() {
+         self.collect(seat, input)?;
+         self.answer_for_agents();
+         return Ok(self.state());
+      }
+
+      if self.is_complete() {
+         return Err(SessionError::Complete);
+      }
+
+      let expected = self.seats_to_answer();
+      if !expected.is_empty() && !expected.contains(seat) {
+         return Err(SessionError::WrongSeat { seat, expected });
+      }
+
+      self.answer(input)
+   }
+
+   /// Returns every [`Input`](Play::Input) that is legal in response to the pending prompt, as enumerated by
+   /// [`LegalMoves::legal_inputs`]. Returns an empty list if the game is over.
+   pub fn legal_inputs(&self) -> Vec<Game::Input> where
+      Game : LegalMoves,
+   {
+      match self.pending() {
+         Some(prompt) => self.host.with_game(|game| game.legal_inputs(prompt).collect()),
+         None => Vec::new(),
+      }
+   }
+
+   /// Returns `true` if `input` is legal in response to the pending prompt. Always returns `false` if the game is over.
+   pub fn is_legal(&self, input : &Game::Input) -> bool where
+      Game : LegalMoves,
+      Game::Input : PartialEq,
+   {
+      match self.pending() {
+         Some(prompt) => self.host.with_game(|game| is_legal_input(&*game, prompt, input)),
+         None => false,
+      }
+   }
+
+   /// Makes the session check every [`Input`](Play::Input) submitted via [`resume`](Self::resume) or
+   /// [`resume_as`](Self::resume_as) against [`LegalMoves::legal_inputs`]. Illegal inputs are **not** passed to the
+   /// game, and [`SessionError::IllegalInput`] is returned instead. Inputs chosen by an [`Agent`] are not checked.
+   pub fn validate_inputs(&mut self) where
+      Game : LegalMoves,
+      Game::Input : PartialEq,
+   {
+      self.is_legal = Some(is_legal_input::<Game>);
+   }
+
+   /// Makes [`resume`](Self::resume) reject every [`Input`](Play::Input) for a prompt that must be answered by
+   /// particular seats (see [`Seated::seats_to_answer`]) with [`SessionError::SeatRequired`], so that these prompts can
+   /// only be answered via [`resume_as`](Self::resume_as). This should be enabled whenever inputs come from players
+   /// who shouldn't be able to answer for each other, e.g. on a server.
+   pub fn require_seats(&mut self) where
+      Game : Seated,
+   {
+      self.required_seats = Some(<Game as Seated>::seats_to_answer);
+   }
+
+   /// Binds an [`Agent`] to `seat`, replacing any agent that was already bound to it. From now on, every prompt that
+   /// can only be answered by seats bound to agents is answered automatically (by the lowest-numbered of these seats),
+   /// and the game keeps running until a prompt for a human seat is yielded (or the game ends). The same goes for
+   /// bound seats asked to answer a simultaneous prompt.
+   ///
+   /// Informational prompts (i.e. those that may be answered from any seat) are never answered by agents.
+   ///
+   /// ⚠️ **IMPORTANT:** An agent that keeps choosing inputs the game rejects will keep being asked forever.
+   pub fn bind(&mut self, seat : Seat, agent : impl Agent<Game> + 'static) where
+      Game : Seated,
+   {
+      self.seats_of = Some(<Game as Seated>::seats_to_answer);
+      self.agents.insert(seat, Box::new(agent));
+      self.answer_for_agents();
+   }
+
+   /// Unbinds the [`Agent`] bound to `seat`, if any, handing control of the seat back to the frontend.
+   pub fn unbind(&mut self, seat : Seat) -> Option<Box<dyn Agent<Game>>> {
+      self.agents.remove(&seat)
+   }
+
+   /// Returns `true` if an [`Agent`] is bound to `seat`.
+   pub fn is_bound(&self, seat : Seat) -> bool {
+      self.agents.contains_key(&seat)
+   }
+
+   /// Makes the session keep a copy of every [`Event`](Play::Event) yielded from now on, until they are taken via
+   /// [`take_events`](Self::take_events). Unlike [`pending`](Self::pending), this includes every prompt answered by
+   /// bound [`Agent`s](Agent) along the way, e.g. so that a server can show each player the moves made by bots. The
+   /// event pending right now is **not** included.
+   pub fn log_events(&mut self) where
+      Game::Event : Clone,
+   {
+      self.log.get_or_insert_with(|| EventLog { events: Vec::new(), clone_event: Game::Event::clone });
+   }
+
+   /// Removes and returns every [`Event`](Play::Event) logged since the last call, in the order they were yielded.
+   /// Nothing is returned unless [`log_events`](Self::log_events) was called first.
+   pub fn take_events(&mut self) -> impl Iterator<Item = Game::Event> + '_ {
+      self.log.iter_mut().flat_map(|log| log.events.drain(..))
+   }
+
+   /// Returns `true` if the session was created with [`recorded`](Self::recorded), and can therefore be
+   /// [forked](Self::fork).
+   pub fn is_recorded(&self) -> bool {
+      self.history.is_some()
+   }
+
+   /// Creates an independent copy of a [recorded](Self::recorded) session at the same pending prompt, e.g. to search
+   /// ahead, preview a "what-if" move, or keep an undo point. The fork and the original can then be resumed separately
+   /// without affecting each other. Returns [`None`] if the session is not being recorded.
+   ///
+   /// Since a coroutine cannot be cloned, the fork is created by replaying every recorded input into a fresh [`Host`]
+   /// holding a copy of the initial game state. This means that [`play`](Play::play) **must** be deterministic, and
+   /// that forking takes longer the further the game has progressed. Unlike starting a new host from a copy of the
+   /// current game state (as [`search`](crate::search) does), this works even for games that keep some of their state
+   /// in the coroutine itself. The fork validates inputs (see [`validate_inputs`](Self::validate_inputs)) and requires
+   /// seats (see [`require_seats`](Self::require_seats)) just like the original, but the following are **not** copied:
+   /// - Any [`Middleware`](crate::Middleware) or other configuration of the original [`Host`].
+   /// - Bound [`Agent`s](Agent).
+   /// - Any [logged events](Self::log_events).
+   /// - Inputs already submitted for a pending simultaneous prompt, which are kept secret until every seat has
+   ///   answered.
+   pub fn fork(&self) -> Option<Self> {
+      let history = self.history.as_ref()?;
+      let host = Host::new((history.clone_game)(&history.initial));
+      let mut fork = Self::new(host).expect("a new host can always be played");
+      fork.seats_of = self.seats_of;
+      fork.required_seats = self.required_seats;
+      fork.is_legal = self.is_legal;
+      fork.history = Some(History {
+         initial: (history.clone_game)(&history.initial),
+         resumes: Vec::with_capacity(history.resumes.len()),
+         clone_game: history.clone_game,
+         clone_resume: history.clone_resume,
+      });
+
+      for resume in history.resumes.iter() {
+         fork.step((history.clone_resume)(resume));
+      }
+      Some(fork)
+   }
+
+   /// Records the input submitted from `seat` for a simultaneous prompt, resuming the game once every asked seat has
+   /// submitted one.
+   fn collect(&mut self, seat : Seat, input : Game::Input) -> Result<(), SessionError> {
+      let Some(asked) = &self.asked else {
+         return Err(SessionError::SeatRequired);
+      };
+
+      if asked.inputs.contains_key(&seat) {
+         return Err(SessionError::AlreadySubmitted { seat });
+      }
+      if !asked.waiting.contains(seat) {
+         let expected = asked.inputs.keys().copied().chain(asked.waiting.iter()).collect();
+         return Err(SessionError::WrongSeat { seat, expected });
+      }
+
+      self.check_legal(&input)?;
+      self.submit(seat, input);
+      Ok(())
+   }
+
+   /// Stores the input submitted from `seat` for a simultaneous prompt, without checking it. Resumes the game once
+   /// every asked seat has submitted one.
+   fn submit(&mut self, seat : Seat, input : Game::Input) {
+      let Some(asked) = &mut self.asked else {
+         return;
+      };
+
+      asked.waiting.remove(seat);
+      asked.inputs.insert(seat, input);
+      if asked.waiting.is_empty() {
+         let inputs = self.asked.take().map(|asked| asked.inputs).unwrap_or_default();
+         self.step(Resume::Inputs(inputs));
+      }
+   }
+
+   /// Resumes the game with `input` for the pending prompt, once it is known to come from the right seat.
+   fn answer(&mut self, input : Game::Input) -> Result<GeneratorState<&Game::Event, &Game::Outcome>, SessionError> {
+      self.check_legal(&input)?;
+      Ok(self.advance(Resume::Input(input)))
+   }
+
+   /// Returns [`SessionError::IllegalInput`] if inputs are being validated, and `input` is not legal.
+   fn check_legal(&self, input : &Game::Input) -> Result<(), SessionError> {
+      match (self.is_legal, self.pending()) {
+         (Some(is_legal), Some(prompt)) if !self.host.with_game(|game| is_legal(&game, prompt, input)) => {
+            Err(SessionError::IllegalInput)
+         },
+         _ => Ok(()),
+      }
+   }
+
+   /// Resumes the coroutine, then checks whether the next prompt must be answered by several seats at once, or can be
+   /// answered by an [`Agent`].
+   fn advance(&mut self, resume : Resume<Game::Input>) -> GeneratorState<&Game::Event, &Game::Outcome> {
+      self.step(resume);
+      self.answer_for_agents();
+      self.state()
+   }
+
+   /// Resumes the coroutine, then checks whether the next prompt must be answered by several seats at once.
+   fn step(&mut self, resume : Resume<Game::Input>) {
+      if let Some(history) = &mut self.history {
+         history.resumes.push((history.clone_resume)(&resume));
+      }
+
+      self.state = self.co.as_mut().resume_with(resume);
+      self.asked = Asked::take(&self.host);
+      if let (Some(log), GeneratorState::Yielded(event)) = (&mut self.log, &self.state) {
+         log.events.push((log.clone_event)(event));
+      }
+   }
+
+   /// Keeps answering pending prompts on behalf of bound [`Agent`s](Agent), until a prompt for a human seat is yielded
+   /// (or the game ends).
+   fn answer_for_agents(&mut self) {
+      let Some(seats_of) = self.seats_of else {
+         return;
+      };
+
+      while let GeneratorState::Yielded(prompt) = &self.state {
+         let game = self.host.borrow_game();
+         let seats = match &self.asked {
+            Some(asked) => asked.waiting.iter().filter(|seat| self.agents.contains_key(seat)).collect(),
+            None => seats_of(&game, prompt),
+         };
+
+         let is_bound = |seat : Seat| self.agents.contains_key(&seat);
+         if seats.is_empty() || (self.asked.is_none() && !seats.iter().all(is_bound)) {
+            return;
+         }
+
+         let mut inputs = Vec::with_capacity(seats.len());
+         for seat in seats.iter() {
+            let agent = self.agents.get_mut(&seat).expect("seat is bound to an agent");
+            inputs.push((seat, agent.choose(&game, prompt)));
+            if self.asked.is_none() {
+               break;
+            }
+         }
+         drop(game);
+
+         for (seat, input) in inputs {
+            if self.asked.is_some() {
+               self.submit(seat, input);
+            }
+            else {
+               self.step(Resume::Input(input));
+            }
+         }
+
+         if self.asked.as_ref().is_some_and(|asked| !asked.inputs.is_empty()) {
+            // Still waiting on human seats to answer a simultaneous prompt.
+            return;
+         }
+      }
+   }
+
+   /// Consumes the [`Session`], returning its [`Outcome`](Play::Outcome) if the game is over.
+   pub fn into_outcome(self) -> Option<Game::Outcome> {
+      match self.state {
+         GeneratorState::Yielded(_) => None,
+         GeneratorState::Complete(outcome) => Some(outcome),
+      }
+   }
+}
+
+/// Returns `true` if `input` is one of the [`LegalMoves`] in response to `prompt`.
+fn is_legal_input<Game>(game : &Game, prompt : &Game::Event, input : &Game::Input) -> bool where
+   Game : LegalMoves,
+   Game::Input : PartialEq,
+{
+   game.legal_inputs(prompt).any(|legal| legal == *input)
+}
+
+impl<Input> Asked<Input> {
+   /// Starts collecting inputs if the game just raised a prompt via [`Context::ask_all`](crate::Context::ask_all).
+   fn take<Game>(host : &Host<Game>) -> Option<Self> where
+      Game : Play<Input = Input>,
+   {
+      host.take_asking().map(|waiting| Self { waiting, inputs: HashMap::new() })
+   }
+}
@@ -0,0 +1,274 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{rng::Rng, LegalMoves, Ranked, Seat, Seated, Session, Standings};
+
+/// Plays many games headlessly, e.g. between bots, and collects aggregate statistics in a [`Report`].
+///
+/// Each game is created by a `setup` function, which is passed a per-game seed and returns a new [`Session`], usually
+/// with an [`Agent`](crate::Agent) bound to each seat. Any prompt that is not answered by an agent is answered with a
+/// random legal input, so unbound seats play randomly.
+///
+/// Since a [`Host`](crate::Host) can't be sent between threads, every thread calls `setup` to create its own sessions.
+/// The seed passed to `setup` only depends on the simulation's seed and the game's index, so the results are the same
+/// regardless of how many threads are used.
+pub struct Simulation<Setup> {
+   setup : Setup,
+   games : usize,
+   threads : usize,
+   seed : u64,
+   max_prompts : usize,
+}
+
+impl<Setup> Simulation<Setup> {
+   /// The default number of prompts answered randomly in a single game before giving up on finishing it.
+   pub const DEFAULT_MAX_PROMPTS : usize = 10_000;
+
+   /// Creates a new [`Simulation`] of `games` games, each created by `setup`. Runs on a single thread by default.
+   pub fn new(games : usize, setup : Setup) -> Self {
+      Self { setup, games, threads: 1, seed: 0, max_prompts: Self::DEFAULT_MAX_PROMPTS }
+   }
+
+   /// Sets the number of threads to play games on.
+   pub fn set_threads(&mut self, threads : usize) {
+      self.threads = threads.max(1);
+   }
+
+   /// Sets the seed from which every game's seed is derived.
+   pub fn set_seed(&mut self, seed : u64) {
+      self.seed = seed;
+   }
+
+   /// Sets the number of prompts answered randomly in a single game before giving up on finishing it. Defaults to
+   /// [`DEFAULT_MAX_PROMPTS`](Self::DEFAULT_MAX_PROMPTS).
+   pub fn set_max_prompts(&mut self, max_prompts : usize) {
+      self.max_prompts = max_prompts;
+   }
+
+   /// Plays every game, returning the combined [`Report`].
+   pub fn run<Game>(&self) -> Report where
+      Setup : Fn(u64) -> Session<Game> + Sync,
+      Game : LegalMoves + Seated + 'static,
+      Game::Input : Clone,
+      Game::Outcome : Ranked,
+   {
+      let threads = self.threads.min(self.games).max(1);
+      let reports : Vec<Report> = std::thread::scope(|scope| {
+         let workers : Vec<_> = (0..threads)
+            .map(|thread| scope.spawn(move || {
+               let mut report = Report::default();
+               for index in (thread..self.games).step_by(threads) {
+                  self.play(index, &mut report);
+               }
+               report
+            }))
+            .collect();
+
+         workers.into_iter().map(|worker| worker.join().expect("simulation thread panicked")).collect()
+      });
+
+      reports.into_iter().fold(Report::default(), |mut total, report| {
+         total.merge(&report);
+         total
+      })
+   }
+
+   /// Plays the game numbered `index`, adding its results to `report`.
+   fn play<Game>(&self, index : usize, report : &mut Report) where
+      Setup : Fn(u64) -> Session<Game>,
+      Game : LegalMoves + Seated + 'static,
+      Game::Input : Clone,
+      Game::Outcome : Ranked,
+   {
+      let seed = Rng::new(self.seed.wrapping_add(index as u64)).next_u64();
+      let mut session = (self.setup)(seed);
+      play_out(&mut session, &mut Rng::new(seed), self.max_prompts);
+
+      let standings = session.outcome().map(Ranked::standings);
+      report.record(standings.as_ref(), session.host().event_count());
+   }
+}
+
+/// Plays `session` until the game is over, answering any prompt not answered by an [`Agent`](crate::Agent) with a
+/// random legal input. Gives up after answering `max_prompts` prompts, or if no legal input is accepted.
+pub(crate) fn play_out<Game>(session : &mut Session<Game>, rng : &mut Rng, max_prompts : usize) where
+   Game : LegalMoves + Seated + 'static,
+   Game::Input : Clone,
+{
+   for _ in 0..max_prompts {
+      if session.is_complete() {
+         break;
+      }
+
+      let inputs = session.legal_inputs();
+      let Some(input) = rng.pick(&inputs).cloned() else {
+         break;
+      };
+
+      let result = match session.seats_to_answer().first() {
+         Some(seat) => session.resume_as(seat, input).map(|_| ()),
+         None => session.resume(input).map(|_| ()),
+      };
+      if result.is_err() {
+         break;
+      }
+   }
+}
+
+/// Statistics for a single [`Seat`] in a [`Report`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SeatReport {
+   /// The number of finished games the seat took part in.
+   pub games : usize,
+
+   /// The number of games the seat finished in sole first place.
+   pub wins : usize,
+
+   /// The number of games the seat shared first place with others.
+   pub draws : usize,
+
+   /// The sum of the seat's places across every game, used to compute the [average](Self::average_place).
+   pub total_place : usize,
+}
+
+impl SeatReport {
+   /// The number of games the seat did not finish in first place.
+   pub fn losses(&self) -> usize {
+      self.games - self.wins - self.draws
+   }
+
+   /// The fraction of games the seat won outright.
+   pub fn win_rate(&self) -> f64 {
+      ratio(self.wins as f64, self.games)
+   }
+
+   /// The seat's average place, where `1.0` is first.
+   pub fn average_place(&self) -> f64 {
+      ratio(self.total_place as f64, self.games)
+   }
+}
+
+/// Aggregate statistics collected by a [`Simulation`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Report {
+   /// The number of games played, including unfinished ones.
+   pub games : usize,
+
+   /// The number of games that were abandoned before they were over.
+   pub unfinished : usize,
+
+   /// The number of finished games where every player tied for first place.
+   pub draws : usize,
+
+   /// The total number of [`Event`s](crate::Play::Event) processed across every game.
+   pub total_events : u64,
+
+   /// The fewest events processed in a single game.
+   pub min_events : u64,
+
+   /// The most events processed in a single game.
+   pub max_events : u64,
+
+   /// Statistics for each seat that placed in at least one game.
+   pub seats : BTreeMap<Seat, SeatReport>,
+}
+
+impl Report {
+   /// The average number of [`Event`s](crate::Play::Event) processed per game, i.e. the average length of a game.
+   pub fn average_events(&self) -> f64 {
+      ratio(self.total_events as f64, self.games)
+   }
+
+   /// Adds the results of a single game, which processed `events` events and ended with `standings` (or was abandoned,
+   /// if [`None`]).
+   pub fn record(&mut self, standings : Option<&Standings>, events : u64) {
+      self.min_events = if self.games == 0 { events } else { self.min_events.min(events) };
+      self.max_events = self.max_events.max(events);
+      self.games += 1;
+      self.total_events += events;
+
+      let Some(standings) = standings else {
+         self.unfinished += 1;
+         return;
+      };
+
+      if standings.is_draw() {
+         self.draws += 1;
+      }
+
+      let winners = standings.winners().count();
+      for placement in standings.iter() {
+         let seat = self.seats.entry(placement.player).or_default();
+         seat.games += 1;
+         seat.total_place += placement.place;
+         match placement.place {
+            1 if winners == 1 => seat.wins += 1,
+            1 => seat.draws += 1,
+            _ => (),
+         }
+      }
+   }
+
+   /// Combines the statistics from `other` into this report.
+   pub fn merge(&mut self, other : &Report) {
+      if other.games == 0 {
+         return;
+      }
+
+      self.min_events = if self.games == 0 { other.min_events } else { self.min_events.min(other.min_events) };
+      self.max_events = self.max_events.max(other.max_events);
+      self.games += other.games;
+      self.unfinished += other.unfinished;
+      self.draws += other.draws;
+      self.total_events += other.total_events;
+
+      for (seat, stats) in other.seats.iter() {
+         let totals = self.seats.entry(*seat).or_default();
+         totals.games += stats.games;
+         totals.wins += stats.wins;
+         totals.draws += stats.draws;
+         totals.total_place += stats.total_place;
+      }
+   }
+
+   /// Formats the report as a JSON object.
+   pub fn to_json(&self) -> String {
+      let mut json = format!(
+         "{{\"games\":{},\"unfinished\":{},\"draws\":{},\"average_events\":{:.3},\"min_events\":{},\"max_events\":{},\
+         \"total_events\":{},\"seats\":[",
+         self.games, self.unfinished, self.draws, self.average_events(), self.min_events, self.max_events,
+         self.total_events);
+
+      for (index, (seat, stats)) in self.seats.iter().enumerate() {
+         let separator = if index > 0 { "," } else { "" };
+         let _ = write!(
+            json,
+            "{separator}{{\"seat\":{},\"games\":{},\"wins\":{},\"draws\":{},\"losses\":{},\"win_rate\":{:.4},\
+            \"average_place\":{:.4}}}",
+            seat.0, stats.games, stats.wins, stats.draws, stats.losses(), stats.win_rate(), stats.average_place());
+      }
+
+      json.push_str("]}");
+      json
+   }
+
+   /// Formats the per-seat statistics as CSV, with a header row.
+   pub fn to_csv(&self) -> String {
+      let mut csv = String::from("seat,games,wins,draws,losses,win_rate,average_place\n");
+      for (seat, stats) in self.seats.iter() {
+         let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{:.4},{:.4}",
+            seat.0, stats.games, stats.wins, stats.draws, stats.losses(), stats.win_rate(), stats.average_place());
+      }
+      csv
+   }
+}
+
+/// Divides `total` by `count`, or returns `0.0` if `count` is zero.
+fn ratio(total : f64, count : usize) -> f64 {
+   if count == 0 { 0.0 } else { total / count as f64 }
+}
@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::cmp::Ordering;
+
+use crate::Seat;
+
+/// Where a single player finished in a game, as part of its [`Standings`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Placement<S = Seat> {
+   /// The player.
+   pub player : S,
+
+   /// The player's final place, starting from `1` for the winner(s). Tied players share the same place, and the place
+   /// after a tie is skipped, e.g. `1, 1, 3`.
+   pub place : usize,
+
+   /// The player's final score, for games that keep score.
+   pub score : Option<i64>,
+
+   /// `true` if the player was knocked out before the game was over.
+   pub is_eliminated : bool,
+}
+
+/// A standard, ranked [`Outcome`](crate::Play::Outcome) for games with any number of players, recording where each
+/// player placed (including ties), and optionally their scores and whether they were eliminated. This allows generic
+/// tooling (e.g. tournament runners and rating systems) to interpret the results of any game that implements
+/// [`Ranked`].
+///
+/// Placements are always ordered from first to last place.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Standings<S = Seat> {
+   placements : Vec<Placement<S>>,
+}
+
+impl<S> Standings<S> {
+   /// Ranks players in groups, from first to last place. Players in the same group are tied.
+   pub fn from_groups<G>(groups : impl IntoIterator<Item = G>) -> Self where
+      G : IntoIterator<Item = S>,
+   {
+      let mut placements = Vec::new();
+      for group in groups {
+         let place = placements.len() + 1;
+         let group = group.into_iter().map(|player| Placement { player, place, score: None, is_eliminated: false });
+         placements.extend(group);
+      }
+      Self { placements }
+   }
+
+   /// Ranks players by score, highest first. Players with equal scores are tied.
+   pub fn from_scores(scores : impl IntoIterator<Item = (S, i64)>) -> Self {
+      let mut scores : Vec<_> = scores.into_iter().collect();
+      scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+      let mut placements : Vec<Placement<S>> = Vec::with_capacity(scores.len());
+      for (index, (player, score)) in scores.into_iter().enumerate() {
+         let place = match placements.last() {
+            Some(last) if last.score == Some(score) => last.place,
+            _ => index + 1,
+         };
+         placements.push(Placement { player, place, score: Some(score), is_eliminated: false });
+      }
+      Self { placements }
+   }
+
+   /// Ranks the `survivors` of an elimination game as tied for first place, followed by the `eliminated` players in
+   /// **reverse** order of elimination, i.e. the player knocked out first places last.
+   pub fn from_eliminations(survivors : impl IntoIterator<Item = S>, eliminated : impl IntoIterator<Item = S>) -> Self {
+      let mut standings = Self::from_groups([survivors]);
+      let mut eliminated : Vec<_> = eliminated.into_iter().collect();
+      while let Some(player) = eliminated.pop() {
+         let place = standings.placements.len() + 1;
+         standings.placements.push(Placement { player, place, score: None, is_eliminated: true });
+      }
+      standings
+   }
+
+   /// Ranks a single `winner` first, with all of the `others` tied for second place.
+   pub fn win(winner : S, others : impl IntoIterator<Item = S>) -> Self {
+      Self::from_groups([vec![winner], others.into_iter().collect()])
+   }
+
+   /// Ranks all `players` as tied for first place.
+   pub fn draw(players : impl IntoIterator<Item = S>) -> Self {
+      Self::from_groups([players])
+   }
+
+   /// Iterates over each player's [`Placement`], from first to last place.
+   pub fn iter(&self) -> impl Iterator<Item = &Placement<S>> {
+      self.placements.iter()
+   }
+
+   /// Returns the number of players that were ranked.
+   pub fn len(&self) -> usize {
+      self.placements.len()
+   }
+
+   /// Returns `true` if no players were ranked.
+   pub fn is_empty(&self) -> bool {
+      self.placements.is_empty()
+   }
+
+   /// Returns the [`Placement`] of `player`, if they were ranked.
+   pub fn get(&self, player : &S) -> Option<&Placement<S>> where
+      S : PartialEq,
+   {
+      self.placements.iter().find(|placement| placement.player == *player)
+   }
+
+   /// Returns the place `player` finished in, if they were ranked.
+   pub fn place_of(&self, player : &S) -> Option<usize> where
+      S : PartialEq,
+   {
+      self.get(player).map(|placement| placement.place)
+   }
+
+   /// Iterates over the players who finished in first place.
+   pub fn winners(&self) -> impl Iterator<Item = &S> {
+      self.placements.iter().take_while(|placement| placement.place == 1).map(|placement| &placement.player)
+   }
+
+   /// Returns `true` if more than one player was ranked, and all of them tied for first place.
+   pub fn is_draw(&self) -> bool {
+      self.len() > 1 && self.placements.iter().all(|placement| placement.place == 1)
+   }
+
+   /// Compares how `a` and `b` placed against each other, returning [`Ordering::Greater`] if `a` finished ahead of
+   /// `b`. Returns [`None`] if either player was not ranked.
+   pub fn compare(&self, a : &S, b : &S) -> Option<Ordering> where
+      S : PartialEq,
+   {
+      Some(self.place_of(b)?.cmp(&self.place_of(a)?))
+   }
+
+   /// Marks `player` as having been eliminated, returning `true` if they were ranked.
+   pub fn set_eliminated(&mut self, player : &S) -> bool where
+      S : PartialEq,
+   {
+      let placement = self.placements.iter_mut().find(|placement| placement.player == *player);
+      placement.map(|placement| placement.is_eliminated = true).is_some()
+   }
+}
+
+/// Trait for [`Outcome`s](crate::Play::Outcome) that can be expressed as [`Standings`], allowing tooling to interpret
+/// the results of a game without knowing anything else about it.
+pub trait Ranked {
+   /// Returns where each [`Seat`] placed.
+   fn standings(&self) -> Standings<Seat>;
+}
+
+impl Ranked for Standings<Seat> {
+   fn standings(&self) -> Standings<Seat> {
+      self.clone()
+   }
+}
@@ -0,0 +1,785 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{
+   fmt::{self, Debug, Display, Write},
+   panic::{catch_unwind, AssertUnwindSafe},
+   path::Path,
+};
+
+use genawaiter::GeneratorState;
+
+use crate::{rng::Rng, session::SessionError, Host, LegalMoves, Play, Seat, Seated, Session};
+
+/// A fluent script for testing a [`Play`] implementation step by step, e.g.:
+///
+/// ```
+/// use posturn::{testing::Script, Context, Host, Play};
+///
+/// /// Asks for guesses until the player guesses `3`, then reports how many guesses it took.
+/// struct Guess;
+///
+/// impl Play for Guess {
+///    type Input = u32;
+///    type Event = &'static str;
+///    type Outcome = u32;
+///
+///    async fn play(ctx : Context<Self>) -> Self::Outcome {
+///       let mut guesses = 1;
+///       while ctx.yield_event("Guess!").await != 3 {
+///          guesses += 1;
+///       }
+///       guesses
+///    }
+/// }
+///
+/// Script::new(Host::new(Guess))
+///    .expect_event("Guess!")
+///    .send(5)
+///    .expect_event("Guess!")
+///    .send(3)
+///    .expect_outcome(2);
+/// ```
+///
+/// Every event, input and outcome is recorded in a transcript, rendered via [`Debug`]. When an expectation fails, the
+/// script panics with the expected and actual values, followed by the transcript so far.
+///
+/// A script drives a [`Session`], so any [`Agent`s](crate::Agent) bound to it answer their prompts as usual. Only the
+/// prompts left for the script to answer appear in the transcript.
+pub struct Script<Game> where
+   Game : Play + 'static,
+{
+   session : Session<Game>,
+   transcript : Vec<String>,
+}
+
+impl<Game> Script<Game> where
+   Game : Play + 'static,
+   Game::Input : Debug,
+   Game::Event : Debug,
+   Game::Outcome : Debug,
+{
+   /// Starts playing the game in `host`.
+   ///
+   /// # Safety
+   /// This function will panic if the game in `host` has already been started.
+   ///
+   #[track_caller]
+   pub fn new(host : Host<Game>) -> Self {
+      Self::from_session(Session::new(host).expect("the game has already been started"))
+   }
+
+   /// Continues playing the game in `session`, e.g. after binding [`Agent`s](crate::Agent) to it.
+   pub fn from_session(session : Session<Game>) -> Self {
+      let mut script = Self { session, transcript: Vec::new() };
+      script.record_state();
+      script
+   }
+
+   /// Returns the [`Session`] being driven by the script.
+   pub fn session(&self) -> &Session<Game> {
+      &self.session
+   }
+
+   /// Stops the script, returning the [`Session`] it was driving.
+   pub fn into_session(self) -> Session<Game> {
+      self.session
+   }
+
+   /// Returns the transcript of every event, input and outcome so far, one per line.
+   pub fn transcript(&self) -> String {
+      self.transcript.iter().fold(String::new(), |mut transcript, line| {
+         let _ = writeln!(transcript, "{line}");
+         transcript
+      })
+   }
+
+   /// Asserts that `expected` is the pending event.
+   #[track_caller]
+   pub fn expect_event(self, expected : Game::Event) -> Self where
+      Game::Event : PartialEq,
+   {
+      match self.session.pending() {
+         Some(event) if *event == expected => (),
+         Some(event) => self.fail(&format!("expected event: {expected:?}\n   found event: {event:?}")),
+         None => self.fail(&format!("expected event: {expected:?}\n   but the game is over")),
+      }
+      self
+   }
+
+   /// Answers the pending prompt with `input`.
+   ///
+   /// # Safety
+   /// This function will panic if the [`Session`] rejects `input`, e.g. because the game is over.
+   ///
+   #[track_caller]
+   pub fn send(mut self, input : Game::Input) -> Self {
+      let line = format!("input: {input:?}");
+      if let Err(error) = self.session.resume(input) {
+         self.fail(&format!("could not send {line}\n   {error:?}"));
+      }
+
+      self.transcript.push(line);
+      self.record_state();
+      self
+   }
+
+   /// Answers the pending prompt with `input`, on behalf of `seat`.
+   ///
+   /// # Safety
+   /// This function will panic if the [`Session`] rejects `input`, e.g. because `seat` was not prompted.
+   ///
+   #[track_caller]
+   pub fn send_as(mut self, seat : Seat, input : Game::Input) -> Self where
+      Game : Seated,
+   {
+      let line = format!("input from seat {}: {input:?}", seat.0);
+      if let Err(error) = self.session.resume_as(seat, input) {
+         self.fail(&format!("could not send {line}\n   {error:?}"));
+      }
+
+      self.transcript.push(line);
+      self.record_state();
+      self
+   }
+
+   /// Answers each prompt in turn with the next of `inputs`, e.g. to replay a recorded game.
+   ///
+   /// # Safety
+   /// This function will panic if the [`Session`] rejects any of the `inputs`, e.g. because the game is over.
+   ///
+   #[track_caller]
+   pub fn send_all(self, inputs : impl IntoIterator<Item = Game::Input>) -> Self {
+      inputs.into_iter().fold(self, Self::send)
+   }
+
+   /// Asserts that the transcript so far matches the golden file at `path`. See [`assert_snapshot`].
+   #[track_caller]
+   pub fn expect_transcript(self, path : impl AsRef<Path>) -> Self {
+      assert_snapshot(path, &self.transcript());
+      self
+   }
+
+   /// Asserts that the game state satisfies `predicate`.
+   #[track_caller]
+   pub fn expect_state(self, predicate : impl FnOnce(&Game) -> bool) -> Self where
+      Game : Debug,
+   {
+      if !predicate(&self.session.host().borrow_game()) {
+         self.fail(&format!("unexpected game state: {:?}", *self.session.host().borrow_game()));
+      }
+      self
+   }
+
+   /// Passes the game state to `inspect`, e.g. to make more detailed assertions about it.
+   pub fn inspect(self, inspect : impl FnOnce(&Game)) -> Self {
+      inspect(&self.session.host().borrow_game());
+      self
+   }
+
+   /// Asserts that the game is over, and ended with `expected`.
+   #[track_caller]
+   pub fn expect_outcome(self, expected : Game::Outcome) -> Self where
+      Game::Outcome : PartialEq,
+   {
+      match self.session.state() {
+         GeneratorState::Complete(outcome) if *outcome == expected => (),
+         GeneratorState::Complete(outcome) => {
+            self.fail(&format!("expected outcome: {expected:?}\n   found outcome: {outcome:?}"))
+         },
+         GeneratorState::Yielded(event) => {
+            self.fail(&format!("expected outcome: {expected:?}\n     found event: {event:?}"))
+         },
+      }
+      self
+   }
+
+   /// Adds the pending event or outcome to the transcript.
+   fn record_state(&mut self) {
+      let line = match self.session.state() {
+         GeneratorState::Yielded(event) => format!("event: {event:?}"),
+         GeneratorState::Complete(outcome) => format!("outcome: {outcome:?}"),
+      };
+      self.transcript.push(line);
+   }
+
+   /// Panics with `message`, followed by the transcript so far.
+   #[track_caller]
+   fn fail(&self, message : &str) -> ! {
+      let transcript : String = self.transcript.iter().map(|line| format!("\n   {line}")).collect();
+      panic!("{message}\n\ntranscript so far:{transcript}");
+   }
+}
+
+/// The environment variable which, when set to anything other than `0`, makes [`assert_snapshot`] overwrite golden
+/// files instead of comparing against them.
+pub const BLESS_VAR : &str = "POSTURN_BLESS";
+
+/// Asserts that `actual` matches the contents of the golden file at `path`, panicking with a line-by-line diff if not.
+///
+/// To accept changes (or create a missing golden file), run the tests again with [`POSTURN_BLESS=1`](BLESS_VAR), then
+/// check in the updated file. Relative paths are resolved against the working directory, which is the crate root when
+/// running `cargo test`.
+///
+/// # Safety
+/// This function will panic if the golden file can't be read, or can't be written while blessing.
+///
+#[track_caller]
+pub fn assert_snapshot(path : impl AsRef<Path>, actual : &str) {
+   let path = path.as_ref();
+   if std::env::var_os(BLESS_VAR).is_some_and(|bless| bless != "0") {
+      if let Some(parent) = path.parent() {
+         std::fs::create_dir_all(parent).expect("failed to create the golden file's directory");
+      }
+      std::fs::write(path, actual).unwrap_or_else(|error| panic!("failed to bless {}: {error}", path.display()));
+      return;
+   }
+
+   let expected = match std::fs::read_to_string(path) {
+      Ok(expected) => expected,
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+         panic!("golden file {} does not exist; run again with {BLESS_VAR}=1 to create it", path.display())
+      },
+      Err(error) => panic!("failed to read {}: {error}", path.display()),
+   };
+
+   if expected != actual {
+      panic!(
+         "{} does not match (- expected, + actual); run again with {BLESS_VAR}=1 to accept the changes:\n{}",
+         path.display(), diff(&expected, actual));
+   }
+}
+
+/// Renders a simple line-by-line diff between `expected` and `actual`.
+fn diff(expected : &str, actual : &str) -> String {
+   let (expected, actual) : (Vec<_>, Vec<_>) = (expected.lines().collect(), actual.lines().collect());
+   let mut diff = String::new();
+   for index in 0..expected.len().max(actual.len()) {
+      match (expected.get(index), actual.get(index)) {
+         (Some(expected), Some(actual)) if expected == actual => {
+            let _ = writeln!(diff, "  {expected}");
+         },
+         (expected, actual) => {
+            if let Some(expected) = expected {
+               let _ = writeln!(diff, "- {expected}");
+            }
+            if let Some(actual) = actual {
+               let _ = writeln!(diff, "+ {actual}");
+            }
+         },
+      }
+   }
+   diff
+}
+
+/// Generates a random [`Input`](Play::Input) in response to the pending prompt of a session, or [`None`] if there are
+/// no inputs to choose from.
+type GenerateFn<Game> = Box<dyn Fn(&Session<Game>, &mut Rng) -> Option<<Game as Play>::Input>>;
+
+/// Checks an invariant of the game state.
+type InvariantFn<Game> = Box<dyn Fn(&Game) -> bool>;
+
+/// Property-based testing for [`Play`] implementations. Plays many games with random inputs, checking that the game
+/// never panics, always finishes within a maximum number of inputs, and upholds every registered
+/// [invariant](Self::check) after every event.
+///
+/// When a game fails, its input log is shrunk by repeatedly removing inputs while the game still fails in the same way,
+/// so that the reported [`Failure`] is a (locally) minimal reproduction. Panics caught while fuzzing are still printed
+/// by the panic hook as usual.
+///
+/// Each game's seed is derived from the fuzzer's seed, so failures are reproducible.
+pub struct Fuzz<Game> where
+   Game : Play + 'static,
+{
+   setup : Box<dyn Fn(u64) -> Game>,
+   generate : GenerateFn<Game>,
+   validate : Option<fn(&mut Session<Game>)>,
+   invariants : Vec<(String, InvariantFn<Game>)>,
+   runs : usize,
+   max_inputs : usize,
+   seed : u64,
+}
+
+impl<Game> Fuzz<Game> where
+   Game : Play + 'static,
+   Game::Input : Clone + Debug,
+   Game::Event : Debug,
+   Game::Outcome : Debug,
+{
+   /// The default number of games to play.
+   pub const DEFAULT_RUNS : usize = 100;
+
+   /// The default number of inputs a game may take before it is considered to never finish.
+   pub const DEFAULT_MAX_INPUTS : usize = 1000;
+
+   /// The number of random bytes each input is built from by [`arbitrary`](Self::arbitrary) fuzzers.
+   #[cfg(feature = "arbitrary")]
+   pub const ARBITRARY_BYTES : usize = 256;
+
+   /// Creates a fuzzer for games created by `setup`, which is passed each game's seed. Every prompt is answered by
+   /// `generate`, given the game state, the pending prompt and a random number generator.
+   pub fn new(
+      setup : impl Fn(u64) -> Game + 'static,
+      generate : impl Fn(&Game, &Game::Event, &mut Rng) -> Game::Input + 'static,
+   ) -> Self {
+      let generate = move |session : &Session<Game>, rng : &mut Rng| {
+         let prompt = session.pending()?;
+         Some(generate(&session.host().borrow_game(), prompt, rng))
+      };
+      Self::with_generator(setup, Box::new(generate), None)
+   }
+
+   /// Creates a fuzzer for games created by `setup`, which is passed each game's seed. Every prompt is answered with a
+   /// random legal input, as enumerated by [`LegalMoves`]. A prompt with no legal inputs counts as a failure.
+   pub fn legal(setup : impl Fn(u64) -> Game + 'static) -> Self where
+      Game : LegalMoves,
+      Game::Input : PartialEq,
+   {
+      let generate = |session : &Session<Game>, rng : &mut Rng| rng.pick(&session.legal_inputs()).cloned();
+      Self::with_generator(setup, Box::new(generate), Some(|session| session.validate_inputs()))
+   }
+
+   /// Creates a fuzzer for games created by `setup`, which is passed each game's seed. Every prompt is answered with an
+   /// input built via [`Arbitrary`](arbitrary::Arbitrary) from [`ARBITRARY_BYTES`](Self::ARBITRARY_BYTES) random
+   /// bytes, so inputs need not be legal. If no input can be built from the bytes, the game fails with
+   /// [`FailureKind::NoInputs`]. Only available with the `arbitrary` feature.
+   #[cfg(feature = "arbitrary")]
+   pub fn arbitrary(setup : impl Fn(u64) -> Game + 'static) -> Self where
+      Game::Input : for<'a> arbitrary::Arbitrary<'a>,
+   {
+      let generate = |session : &Session<Game>, rng : &mut Rng| {
+         session.pending()?;
+         let bytes : Vec<_> = (0..Self::ARBITRARY_BYTES / 8).flat_map(|_| rng.next_u64().to_le_bytes()).collect();
+         arbitrary::Arbitrary::arbitrary(&mut arbitrary::Unstructured::new(&bytes)).ok()
+      };
+      Self::with_generator(setup, Box::new(generate), None)
+   }
+
+   /// Creates a fuzzer that answers prompts with `generate`, and prepares each session with `validate`.
+   fn with_generator(
+      setup : impl Fn(u64) -> Game + 'static,
+      generate : GenerateFn<Game>,
+      validate : Option<fn(&mut Session<Game>)>,
+   ) -> Self {
+      Self {
+         setup: Box::new(setup),
+         generate,
+         validate,
+         invariants: Vec::new(),
+         runs: Self::DEFAULT_RUNS,
+         max_inputs: Self::DEFAULT_MAX_INPUTS,
+         seed: 0,
+      }
+   }
+
+   /// Registers an invariant called `name`, which must hold for the game state at the start of the game and after
+   /// every event.
+   pub fn check(&mut self, name : impl Into<String>, invariant : impl Fn(&Game) -> bool + 'static) {
+      self.invariants.push((name.into(), Box::new(invariant)));
+   }
+
+   /// Sets the number of games to play. Defaults to [`DEFAULT_RUNS`](Self::DEFAULT_RUNS).
+   pub fn set_runs(&mut self, runs : usize) {
+      self.runs = runs;
+   }
+
+   /// Sets the number of inputs a game may take before it is considered to never finish. Defaults to
+   /// [`DEFAULT_MAX_INPUTS`](Self::DEFAULT_MAX_INPUTS).
+   pub fn set_max_inputs(&mut self, max_inputs : usize) {
+      self.max_inputs = max_inputs;
+   }
+
+   /// Sets the seed from which every game's seed is derived.
+   pub fn set_seed(&mut self, seed : u64) {
+      self.seed = seed;
+   }
+
+   /// Plays every game, returning the first (shrunk) [`Failure`], if any.
+   pub fn run(&self) -> Result<(), Failure<Game>> {
+      let mut seeds = Rng::new(self.seed);
+      for _ in 0..self.runs {
+         let seed = seeds.next_u64();
+         let mut rng = Rng::new(seed);
+         let mut inputs = Vec::new();
+         let result = self.play(seed, |session| {
+            let input = (self.generate)(session, &mut rng)?;
+            inputs.push(input.clone());
+            Some(input)
+         });
+
+         if let Err(kind) = result {
+            return Err(self.shrink(seed, inputs, kind));
+         }
+      }
+      Ok(())
+   }
+
+   /// Plays every game, panicking with the first (shrunk) [`Failure`], if any.
+   ///
+   /// # Safety
+   /// This function will panic if any game fails.
+   ///
+   #[track_caller]
+   pub fn assert(&self) {
+      if let Err(failure) = self.run() {
+         panic!("{failure}");
+      }
+   }
+
+   /// Replays the game created from `seed`, answering prompts with `inputs`, and returns its transcript.
+   pub fn replay(&self, seed : u64, inputs : &[Game::Input]) -> (Result<(), FailureKind>, String) {
+      let mut transcript = String::new();
+      let mut inputs = inputs.iter().cloned();
+      let result = self.play_logged(seed, &mut transcript, |_| inputs.next(), false);
+      (result, transcript)
+   }
+
+   /// Plays the game created from `seed`, answering prompts with `next_input` until it returns [`None`].
+   fn play(
+      &self,
+      seed : u64,
+      next_input : impl FnMut(&Session<Game>) -> Option<Game::Input>,
+   ) -> Result<(), FailureKind> {
+      self.play_logged(seed, &mut String::new(), next_input, true)
+   }
+
+   /// Like [`play`](Self::play), but writes a transcript of the game to `transcript`. If `must_finish` is `true`,
+   /// running out of inputs before the game is over counts as a failure.
+   fn play_logged(
+      &self,
+      seed : u64,
+      transcript : &mut String,
+      mut next_input : impl FnMut(&Session<Game>) -> Option<Game::Input>,
+      must_finish : bool,
+   ) -> Result<(), FailureKind> {
+      let result = catch_unwind(AssertUnwindSafe(|| {
+         let mut session = Session::new(Host::new((self.setup)(seed))).expect("a new game can always be started");
+         if let Some(validate) = self.validate {
+            validate(&mut session);
+         }
+
+         for step in 0.. {
+            let _ = match session.state() {
+               GeneratorState::Yielded(event) => writeln!(transcript, "event: {event:?}"),
+               GeneratorState::Complete(outcome) => writeln!(transcript, "outcome: {outcome:?}"),
+            };
+            self.check_invariants(&session)?;
+
+            if session.is_complete() {
+               return Ok(());
+            }
+            if step >= self.max_inputs {
+               return Err(FailureKind::Unfinished(self.max_inputs));
+            }
+
+            let Some(input) = next_input(&session) else {
+               return if must_finish { Err(FailureKind::NoInputs) } else { Ok(()) };
+            };
+
+            let _ = writeln!(transcript, "input: {input:?}");
+            if let Err(error) = session.resume(input) {
+               return Err(FailureKind::Rejected(format!("{error:?}")));
+            }
+         }
+         unreachable!("the loop only ends by returning");
+      }));
+
+      result.unwrap_or_else(|payload| {
+         let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "<non-string panic payload>".into());
+         Err(FailureKind::Panic(message))
+      })
+   }
+
+   /// Checks every invariant against the game state in `session`.
+   fn check_invariants(&self, session : &Session<Game>) -> Result<(), FailureKind> {
+      let game = session.host().borrow_game();
+      match self.invariants.iter().find(|(_, invariant)| !invariant(&game)) {
+         Some((name, _)) => Err(FailureKind::Invariant(name.clone())),
+         None => Ok(()),
+      }
+   }
+
+   /// Shrinks the `inputs` of a game created from `seed` that failed with `kind`, by removing ever smaller chunks of
+   /// inputs while the game still fails the same way.
+   fn shrink(&self, seed : u64, mut inputs : Vec<Game::Input>, kind : FailureKind) -> Failure<Game> {
+      let fails = |inputs : &[Game::Input]| {
+         let mut inputs = inputs.iter().cloned();
+         let result = self.play_logged(seed, &mut String::new(), |_| inputs.next(), false);
+         result.is_err_and(|error| error.is_same_kind(&kind))
+      };
+
+      let mut chunk = inputs.len().div_ceil(2);
+      while chunk > 0 {
+         let mut start = 0;
+         while start < inputs.len() {
+            let end = (start + chunk).min(inputs.len());
+            let candidate : Vec<_> = inputs[..start].iter().chain(&inputs[end..]).cloned().collect();
+            if fails(&candidate) {
+               inputs = candidate;
+            }
+            else {
+               start += chunk;
+            }
+         }
+         chunk /= 2;
+      }
+
+      let (result, transcript) = self.replay(seed, &inputs);
+      Failure { seed, kind: result.err().unwrap_or(kind), inputs, transcript }
+   }
+}
+
+/// How a game failed while [fuzzing](Fuzz).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FailureKind {
+   /// The game panicked with this message.
+   Panic(String),
+
+   /// The [invariant](Fuzz::check) with this name did not hold.
+   Invariant(String),
+
+   /// The game was not over after this many inputs.
+   Unfinished(usize),
+
+   /// The game was not over, but there were no inputs to choose from.
+   NoInputs,
+
+   /// The [`Session`] rejected an input, for this reason.
+   Rejected(String),
+}
+
+impl FailureKind {
+   /// Returns `true` if `self` and `other` are the same kind of failure, e.g. the same invariant not holding. Panics
+   /// count as the same kind of failure regardless of their message.
+   fn is_same_kind(&self, other : &Self) -> bool {
+      match (self, other) {
+         (Self::Panic(_), Self::Panic(_)) => true,
+         (Self::Rejected(_), Self::Rejected(_)) => true,
+         (a, b) => a == b,
+      }
+   }
+}
+
+impl Display for FailureKind {
+   fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         Self::Panic(message) => write!(f, "the game panicked: {message}"),
+         Self::Invariant(name) => write!(f, "the invariant `{name}` did not hold"),
+         Self::Unfinished(max_inputs) => write!(f, "the game was not over after {max_inputs} inputs"),
+         Self::NoInputs => write!(f, "there were no inputs to choose from"),
+         Self::Rejected(error) => write!(f, "an input was rejected: {error}"),
+      }
+   }
+}
+
+/// A game that failed while [fuzzing](Fuzz), shrunk to a minimal reproduction.
+pub struct Failure<Game> where
+   Game : Play,
+{
+   /// The seed passed to the game's `setup` function.
+   pub seed : u64,
+
+   /// How the game failed.
+   pub kind : FailureKind,
+
+   /// The inputs that reproduce the failure, when sent in order to the game created from [`seed`](Self::seed).
+   pub inputs : Vec<Game::Input>,
+
+   /// A transcript of the shrunk game, up to the failure.
+   pub transcript : String,
+}
+
+impl<Game> Debug for Failure<Game> where
+   Game : Play,
+   Game::Input : Debug,
+{
+   fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+      f.debug_struct("Failure")
+         .field("seed", &self.seed)
+         .field("kind", &self.kind)
+         .field("inputs", &self.inputs)
+         .field("transcript", &self.transcript)
+         .finish()
+   }
+}
+
+impl<Game> Display for Failure<Game> where
+   Game : Play,
+   Game::Input : Debug,
+{
+   fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{} (seed {}, inputs {:?})\n\ntranscript:\n{}", self.kind, self.seed, self.inputs, self.transcript)
+   }
+}
+
+/// A contract between a [`Play`] implementation and its [`Host`] that was broken, as found by [`check_conformance`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConformanceError {
+   /// An event was yielded without being handled exactly once, i.e. the [`Host`]'s
+   /// [`event_count`](Host::event_count) went up by `handled` instead of `1`.
+   NotHandled {
+      /// The step the event was yielded at.
+      step : usize,
+
+      /// The yielded event, rendered via [`Debug`].
+      event : String,
+
+      /// The number of events handled since the previous step.
+      handled : u64,
+   },
+
+   /// Applying a yielded event to a mirror of the game via [`process_event`](Host::process_event) left the mirror in a
+   /// different state than the game, e.g. because [`play`](Play::play) changed the game state outside of
+   /// [`handle_event`](Play::handle_event). This breaks [replication](crate::replication) and
+   /// [lockstep](crate::lockstep) play.
+   MirrorDiverged {
+      /// The step the event was yielded at.
+      step : usize,
+
+      /// The yielded event, rendered via [`Debug`].
+      event : String,
+
+      /// The state of the game, rendered via [`Debug`].
+      expected : String,
+
+      /// The state of the mirror, rendered via [`Debug`].
+      actual : String,
+   },
+
+   /// Replaying the same inputs from the same initial state produced a different transcript or final state, i.e. the
+   /// game is not deterministic.
+   ReplayDiverged {
+      /// The first step that differed. The final state is compared as one last step after the outcome.
+      step : usize,
+
+      /// The original transcript's line for this step.
+      expected : String,
+
+      /// The replayed transcript's line for this step.
+      actual : String,
+   },
+
+   /// The game would not accept one of the inputs, e.g. because the prompt was raised via
+   /// [`Context::ask_all`](crate::Context::ask_all), whose inputs must be submitted seat by seat.
+   Rejected {
+      /// The step the input was supplied at.
+      step : usize,
+
+      /// The reason the input was rejected.
+      error : SessionError,
+   },
+}
+
+impl Display for ConformanceError {
+   fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         Self::NotHandled { step, event, handled } => {
+            write!(f, "step {step}: {event} was yielded after being handled {handled} times instead of once")
+         },
+         Self::MirrorDiverged { step, event, expected, actual } => {
+            write!(f, "step {step}: processing {event} gave a mirror {actual} instead of {expected}")
+         },
+         Self::ReplayDiverged { step, expected, actual } => {
+            write!(f, "step {step}: replaying gave {actual} instead of {expected}")
+         },
+         Self::Rejected { step, error } => write!(f, "step {step}: the input was rejected with {error:?}"),
+      }
+   }
+}
+
+/// Plays `game` to completion (or until it runs out of `inputs`), and checks that it upholds the contracts that the
+/// rest of posturn relies on:
+///
+/// 1. Every yielded event has been handled exactly once, via [`handle_event`](Play::handle_event), before it is
+///    yielded.
+/// 2. Processing the yielded events with [`process_event`](Host::process_event) on a mirror of the initial game state
+///    keeps the mirror in the same state as the game after every event, so that games can be replicated.
+/// 3. Replaying the same `inputs` on a copy of the initial game state yields the same events and outcome, and leaves
+///    the game in the same final state.
+///
+/// Returns the first [`ConformanceError`] found, if any. The steps in errors count yielded events and the outcome,
+/// starting from `0`.
+pub fn check_conformance<Game>(
+   game : Game,
+   inputs : impl IntoIterator<Item = Game::Input>,
+) -> Result<(), ConformanceError> where
+   Game : Play + Clone + PartialEq + Debug + 'static,
+   Game::Input : Clone + Debug,
+   Game::Event : Clone + Debug,
+   Game::Outcome : Debug,
+{
+   let inputs : Vec<_> = inputs.into_iter().collect();
+   let host = Host::new(game.clone());
+   let mirror = Host::new(game.clone());
+   let mut transcript = play_conformance(&host, &inputs, |step, event, handled| {
+      if handled != 1 {
+         return Err(ConformanceError::NotHandled { step, event: format!("{event:?}"), handled });
+      }
+
+      // The mirror has no middleware, so it never drops events.
+      let _ = mirror.process_event(&mut event.clone());
+      let (expected, actual) = (host.clone_game(), mirror.clone_game());
+      if expected != actual {
+         let (event, expected, actual) = (format!("{event:?}"), format!("{expected:?}"), format!("{actual:?}"));
+         return Err(ConformanceError::MirrorDiverged { step, event, expected, actual });
+      }
+      Ok(())
+   })?;
+   transcript.push(format!("state: {:?}", host.clone_game()));
+
+   let replay = Host::new(game);
+   let mut replayed = play_conformance(&replay, &inputs, |_, _, _| Ok(()))?;
+   replayed.push(format!("state: {:?}", replay.clone_game()));
+
+   for step in 0..transcript.len().max(replayed.len()) {
+      let (expected, actual) = (transcript.get(step), replayed.get(step));
+      if expected != actual {
+         let describe = |line : Option<&String>| line.cloned().unwrap_or_else(|| "nothing".into());
+         return Err(ConformanceError::ReplayDiverged { step, expected: describe(expected), actual: describe(actual) });
+      }
+   }
+   Ok(())
+}
+
+/// Plays the game in `host` with `inputs`, calling `check` with the step, event and number of events handled for every
+/// yielded event. Returns a transcript of every event and the outcome.
+fn play_conformance<Game>(
+   host : &Host<Game>,
+   inputs : &[Game::Input],
+   mut check : impl FnMut(usize, &Game::Event, u64) -> Result<(), ConformanceError>,
+) -> Result<Vec<String>, ConformanceError> where
+   Game : Play + 'static,
+   Game::Input : Clone,
+   Game::Event : Debug,
+   Game::Outcome : Debug,
+{
+   let mut session = Session::new(host.clone()).expect("a new game can always be started");
+   let mut inputs = inputs.iter().cloned();
+   let mut transcript = Vec::new();
+   let mut handled = 0;
+
+   loop {
+      let step = transcript.len();
+      match session.state() {
+         GeneratorState::Yielded(event) => {
+            check(step, event, host.event_count() - handled)?;
+            transcript.push(format!("event: {event:?}"));
+         },
+         GeneratorState::Complete(outcome) => {
+            transcript.push(format!("outcome: {outcome:?}"));
+            return Ok(transcript);
+         },
+      }
+
+      handled = host.event_count();
+      let Some(input) = inputs.next() else {
+         return Ok(transcript);
+      };
+      if let Err(error) = session.resume(input) {
+         return Err(ConformanceError::Rejected { step, error });
+      }
+   }
+}
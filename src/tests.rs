@@ -3,8 +3,19 @@
 // SPDX-License-Identifier: MIT
 
 use std::{cmp::Ordering, string::String};
-use genawaiter::{Generator, GeneratorState};
-use crate::{Context, Host, Play};
+use genawaiter::{Coroutine, Generator, GeneratorState};
+use crate::{
+   checksum::{Desync, StateHasher},
+   mcts::{Budget, Clock, Determinize, Mcts},
+   net::{MemoryTransport, Transport, Wire, WireError},
+   prediction::{Predict, Predictor, Reconciliation},
+   redact::Redact,
+   replication::{Broadcaster, Mirror, Replicator},
+   rng::Rng,
+   trigger::TriggerOverflow,
+   Context, Host, LegalMoves, Play, Ranked, Seat, Seated, Seats, Session, Standings, StateHash, TakeTurns, Triggers,
+   TryHandleEvent, TurnOrder,
+};
 
 /// Represents input received from a player in a game of [`RoShamBo`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -53,7 +64,7 @@ enum Outcome {
 }
 
 /// The two fields are player 1's choice and player 2's choice, respectively.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 struct RoShamBo(Choice, Choice);
 
 impl Play for RoShamBo {
@@ -124,4 +135,1528 @@ fn test_ro_sham_bo(game : RoShamBo, expected_msg : String, expected_outcome : Ou
    assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg("Bo!".into())));
    assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg(expected_msg)));
    assert_eq!(co.as_mut().resume(), GeneratorState::Complete(expected_outcome));
-}
\ No newline at end of file
+}
+
+/// A game that keeps a running total of every number it is told about. Used to test event processing.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct Tally {
+   total : u32,
+   handled : u32,
+}
+
+impl Play for Tally {
+   type Input = ();
+   type Event = u32;
+   type Outcome = u32;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      for n in 1..=3 {
+         ctx.try_yield_event(n).await;
+      }
+
+      ctx.host.borrow_game().total
+   }
+
+   fn handle_event(&mut self, event : &mut <Self as Play>::Event) {
+      self.total += *event;
+      self.handled += 1;
+   }
+}
+
+impl StateHash for Tally {
+   fn state_hash(&self) -> u64 {
+      StateHasher::hash_of(self)
+   }
+}
+
+impl Wire for Tally {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.total.encode(out);
+      self.handled.encode(out);
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      Ok(Self { total: Wire::decode(input)?, handled: Wire::decode(input)? })
+   }
+}
+
+impl Predict for Tally {
+   fn predict(&self, _input : &<Self as Play>::Input) -> Vec<<Self as Play>::Event> {
+      // Games of tally always count upwards.
+      vec![self.handled + 1]
+   }
+}
+
+impl TryHandleEvent for Tally {
+   type EventError = u32;
+
+   fn try_handle_event(&mut self, event : &mut <Self as Play>::Event) -> Result<(), Self::EventError> {
+      // Zero is never a legal number to tally.
+      if *event == 0 {
+         return Err(*event);
+      }
+
+      self.handle_event(event);
+      Ok(())
+   }
+}
+
+impl Seated for Tally {
+   fn seats_to_answer(&self, _prompt : &<Self as Play>::Event) -> Seats {
+      Seats::EMPTY
+   }
+}
+
+#[test]
+fn test_middleware_runs_in_order() {
+   use futures::pin_mut;
+   use crate::middleware::Intercept;
+
+   let host = Host::new(RoShamBo(Choice::Rock, Choice::Scissors));
+
+   // "Localize" the first message, then shout every message.
+   host.push_middleware(|_ : &RoShamBo, event : &mut Msg| {
+      if event.0 == "Ro!" {
+         *event = Msg("Piedra!".into());
+      }
+      Intercept::Continue
+   });
+   host.push_middleware(|_ : &RoShamBo, event : &mut Msg| {
+      event.0 = event.0.to_uppercase();
+      Intercept::Continue
+   });
+
+   let co = host.play().unwrap();
+   pin_mut!(co);
+
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg("PIEDRA!".into())));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg("SHAM!".into())));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg("BO!".into())));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(Msg("ROCK BEATS SCISSORS.".into())));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Complete(Outcome::Win));
+}
+
+#[test]
+fn test_middleware_can_drop_events() {
+   use futures::pin_mut;
+   use crate::middleware::Intercept;
+
+   let host = Host::new(Tally::default());
+   host.push_middleware(|_ : &Tally, event : &mut u32| {
+      if *event == 2 { Intercept::Drop } else { Intercept::Continue }
+   });
+
+   // Dropped events are never yielded, and the game never sees them.
+   let co = host.play().unwrap();
+   pin_mut!(co);
+
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(1));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Yielded(3));
+   assert_eq!(co.as_mut().resume(), GeneratorState::Complete(4));
+
+   // The same applies to externally supplied events.
+   assert!(!host.process_event(&mut 2));
+   assert!(host.process_event(&mut 5));
+   assert_eq!(host.clone_game(), Tally { total: 9, handled: 3 });
+}
+
+#[test]
+fn test_try_process_event_rejects_illegal_events() {
+   use crate::middleware::Intercept;
+
+   let host = Host::new(Tally::default());
+   assert_eq!(host.try_process_event(&mut 3), Ok(true));
+   assert_eq!(host.try_process_event(&mut 0), Err(0));
+   assert_eq!(host.clone_game(), Tally { total: 3, handled: 1 });
+
+   // Events dropped by middleware are not errors.
+   host.push_middleware(|_ : &Tally, _ : &mut u32| Intercept::Drop);
+   assert_eq!(host.try_process_event(&mut 0), Ok(false));
+   assert_eq!(host.clone_game(), Tally { total: 3, handled: 1 });
+}
+
+/// A game that counts down from a starting number by having each number trigger the next one.
+#[derive(Clone, Debug, Default)]
+struct Countdown {
+   start : u32,
+}
+
+impl Play for Countdown {
+   type Input = u32;
+   type Event = u32;
+   type Outcome = u32;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      let start = ctx.host.borrow_game().start;
+      ctx.yield_event(start).await
+   }
+
+   fn trigger(&self, event : &<Self as Play>::Event, triggers : &mut Triggers<Self>) {
+      // Overflows are collected by the host, so there's nothing for the game to do about them here.
+      if *event > 0 {
+         let _ = triggers.push(event - 1);
+      }
+   }
+}
+
+#[test]
+fn test_triggers_are_yielded_before_resuming() {
+   use futures::pin_mut;
+
+   let host = Host::new(Countdown { start: 3 });
+   let co = host.play().unwrap();
+   pin_mut!(co);
+
+   // Only the input supplied for the original event makes it back to the game. Inputs for triggered events are lost.
+   assert_eq!(co.as_mut().resume_with(0), GeneratorState::Yielded(3));
+   assert_eq!(co.as_mut().resume_with(30), GeneratorState::Yielded(2));
+   assert_eq!(co.as_mut().resume_with(20), GeneratorState::Yielded(1));
+   assert_eq!(co.as_mut().resume_with(10), GeneratorState::Yielded(0));
+   assert_eq!(co.as_mut().resume_with(42), GeneratorState::Complete(30));
+   assert!(host.take_trigger_overflows().is_empty());
+}
+
+#[test]
+fn test_triggers_stop_at_max_depth() {
+   use futures::pin_mut;
+
+   let host = Host::new(Countdown { start: 100 });
+   host.set_max_trigger_depth(2);
+
+   let co = host.play().unwrap();
+   pin_mut!(co);
+
+   assert_eq!(co.as_mut().resume_with(0), GeneratorState::Yielded(100));
+   assert_eq!(co.as_mut().resume_with(7), GeneratorState::Yielded(99));
+   assert_eq!(co.as_mut().resume_with(0), GeneratorState::Yielded(98));
+   assert_eq!(co.as_mut().resume_with(0), GeneratorState::Complete(7));
+   assert_eq!(host.take_trigger_overflows(), vec![TriggerOverflow { max_depth: 2 }]);
+   assert!(host.take_trigger_overflows().is_empty());
+}
+
+#[test]
+fn test_checksums_detect_desyncs() {
+   use futures::pin_mut;
+
+   let server = Host::new(Tally::default());
+   server.enable_checksums();
+
+   // The second client starts out with a different state, but won't notice until the checksums are compared.
+   let in_sync = Host::new(Tally::default());
+   let out_of_sync = Host::new(Tally { total: 1, handled: 0 });
+
+   let co = server.play().unwrap();
+   pin_mut!(co);
+
+   let mut index = 0;
+   while let GeneratorState::Yielded(event) = co.as_mut().resume() {
+      let checked = server.checked(event);
+      assert_eq!(checked.index, index);
+      assert_eq!(Some(checked.checksum), server.checksum());
+
+      assert_eq!(in_sync.process_checked(checked), Ok(true));
+      let err = out_of_sync.process_checked(checked).unwrap_err();
+      assert_eq!(err, Desync { index, expected: checked.checksum, actual: out_of_sync.with_game(|g| g.state_hash()) });
+
+      index += 1;
+   }
+
+   assert_eq!(server.event_count(), 3);
+   assert_eq!(in_sync.clone_game(), server.clone_game());
+}
+
+/// A [`Transport`] wrapper that delivers frames in reverse order, and can be told to lose frames it sends.
+struct Unreliable<T> {
+   inner : T,
+   drop_sends : Vec<usize>,
+   sent : usize,
+   received : Vec<Vec<u8>>,
+}
+
+impl<T> Unreliable<T> {
+   fn new(inner : T, drop_sends : Vec<usize>) -> Self {
+      Self { inner, drop_sends, sent: 0, received: Vec::new() }
+   }
+}
+
+impl<T : Transport> Transport for Unreliable<T> {
+   fn send(&mut self, frame : &[u8]) -> std::io::Result<()> {
+      self.sent += 1;
+      if self.drop_sends.contains(&(self.sent - 1)) { Ok(()) } else { self.inner.send(frame) }
+   }
+
+   fn try_recv(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+      loop {
+         match self.inner.try_recv() {
+            Ok(Some(frame)) => self.received.push(frame),
+            Ok(None) => break,
+            Err(_) if !self.received.is_empty() => break,
+            Err(err) => return Err(err),
+         }
+      }
+      Ok(self.received.pop())
+   }
+}
+
+/// Runs a game of [`Tally`] on the server side of a [`Replicator`], returning the final game state.
+fn replicate_tally<T : Transport>(transport : T) -> Tally {
+   use futures::pin_mut;
+
+   let host = Host::new(Tally::default());
+   host.enable_checksums();
+
+   let mut server = Replicator::new(host.clone(), transport);
+   let co = host.play().unwrap();
+   pin_mut!(co);
+
+   while let GeneratorState::Yielded(event) = co.as_mut().resume() {
+      server.publish(&event).unwrap();
+   }
+
+   host.clone_game()
+}
+
+#[test]
+fn test_mirror_applies_events_in_order() {
+   let (server_end, client_end) = MemoryTransport::pair();
+   let expected = replicate_tally(server_end);
+
+   // The client receives the frames in reverse order, but the mirror still applies them in order.
+   let client_host = Host::new(Tally::default());
+   client_host.enable_checksums();
+   let mut mirror = Mirror::new(client_host, Unreliable::new(client_end, vec![]));
+
+   assert_eq!(mirror.poll().unwrap(), vec![1, 2, 3]);
+   assert_eq!(mirror.next_seq(), 3);
+   assert_eq!(mirror.host().clone_game(), expected);
+   assert_eq!(mirror.last_desync(), None);
+}
+
+#[test]
+fn test_mirror_resyncs_after_lost_events() {
+   let (server_end, client_end) = MemoryTransport::pair();
+   let host = Host::new(Tally::default());
+   let mut server = Replicator::new(host.clone(), Unreliable::new(server_end, vec![0]));
+   let mut mirror = Mirror::new(Host::new(Tally::default()), client_end);
+   mirror.set_max_buffered(1);
+
+   // The first event is lost, so the mirror can only buffer what comes after it.
+   for mut event in [5, 6, 7] {
+      assert!(host.process_event(&mut event));
+      server.publish(&event).unwrap();
+   }
+   assert_eq!(mirror.poll().unwrap(), vec![]);
+   assert!(mirror.is_resyncing());
+
+   // The snapshot brings the client up to date. Anything published afterwards is applied as usual.
+   server.poll().unwrap();
+   assert!(host.process_event(&mut 8));
+   server.publish(&8).unwrap();
+
+   assert_eq!(mirror.poll().unwrap(), vec![8]);
+   assert!(!mirror.is_resyncing());
+   assert_eq!(mirror.host().clone_game(), Tally { total: 26, handled: 4 });
+}
+
+#[test]
+fn test_mirror_resyncs_after_desync() {
+   let (server_end, client_end) = MemoryTransport::pair();
+   let host = Host::new(Tally::default());
+   host.enable_checksums();
+   let mut server = Replicator::new(host.clone(), server_end);
+
+   let client_host = Host::new(Tally { total: 100, handled: 0 });
+   client_host.enable_checksums();
+   let mut mirror = Mirror::new(client_host, client_end);
+
+   assert!(host.process_event(&mut 1));
+   server.publish(&1).unwrap();
+   assert_eq!(mirror.poll().unwrap(), vec![1]);
+   assert_eq!(mirror.last_desync().map(|desync| desync.index), Some(0));
+   assert!(mirror.is_resyncing());
+
+   server.poll().unwrap();
+   assert_eq!(mirror.poll().unwrap(), vec![]);
+   assert_eq!(mirror.host().clone_game(), host.clone_game());
+   assert_eq!(mirror.host().checksum(), host.checksum());
+   assert_eq!(mirror.host().event_count(), 1);
+
+   // Changes the server makes outside of `handle_event` are covered by the checksum of the next event it publishes.
+   host.borrow_game_mut().total += 10;
+   assert!(host.process_event(&mut 2));
+   server.publish(&2).unwrap();
+   assert_eq!(mirror.poll().unwrap(), vec![2]);
+   assert_eq!(mirror.last_desync().map(|desync| desync.index), Some(1));
+}
+
+#[test]
+fn test_mirror_keeps_events_applied_before_an_error() {
+   let (server_end, mut client_end) = MemoryTransport::pair();
+   let expected = replicate_tally(server_end);
+   let mut frames = Vec::new();
+   while let Ok(Some(frame)) = client_end.try_recv() {
+      frames.push(frame);
+   }
+
+   // A garbled frame shows up in the middle of the stream.
+   let (mut feed, mirror_end) = MemoryTransport::pair();
+   let mut mirror = Mirror::new(Host::new(Tally::default()), mirror_end);
+   for frame in [&frames[0], &frames[1], &vec![0xff], &frames[2]] {
+      feed.send(frame).unwrap();
+   }
+
+   // The events applied before the garbled frame are reported first, then the error, then the rest of the stream.
+   assert_eq!(mirror.poll().unwrap(), vec![1, 2]);
+   assert!(mirror.poll().is_err());
+   assert_eq!(mirror.poll().unwrap(), vec![3]);
+   assert_eq!(mirror.host().clone_game(), expected);
+}
+
+#[test]
+fn test_mirror_over_tcp() {
+   use std::net::{TcpListener, TcpStream};
+   use crate::net::TcpTransport;
+
+   let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+   let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+   let (server, _) = listener.accept().unwrap();
+
+   let expected = replicate_tally(TcpTransport::new(server).unwrap());
+
+   let client_host = Host::new(Tally::default());
+   client_host.enable_checksums();
+   let mut mirror = Mirror::new(client_host, TcpTransport::new(client).unwrap());
+
+   // The frames may take a moment to arrive, and the server hanging up shows up as an error once they're all read.
+   let mut applied = Vec::new();
+   while applied.len() < 3 {
+      applied.extend(mirror.poll().unwrap());
+   }
+
+   assert_eq!(applied, vec![1, 2, 3]);
+   assert_eq!(mirror.host().clone_game(), expected);
+   assert!(mirror.poll().is_err());
+}
+
+/// Two players take turns adding `1`, `2` or `3` to a running total. Whoever brings the total to [`Race::GOAL`] wins.
+/// Each event is the [`Seat`] of the player whose turn it is, and the outcome is the winning [`Seat`]. Whose turn it is
+/// is part of the game state, so a copy of a race picks up where the original left off.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct Race {
+   total : u32,
+   turn : Seat,
+}
+
+impl Race {
+   const GOAL : u32 = 10;
+}
+
+impl Play for Race {
+   type Input = u32;
+   type Event = Seat;
+   type Outcome = Seat;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      loop {
+         let seat = ctx.host.borrow_game().turn;
+         let amount = ctx.yield_event(seat).await.clamp(1, 3);
+         let total = {
+            let mut game = ctx.host.borrow_game_mut();
+            game.total += amount;
+            game.total
+         };
+
+         if total >= Self::GOAL {
+            return seat;
+         }
+
+         ctx.host.borrow_game_mut().turn = Seat(1 - seat.0);
+      }
+   }
+}
+
+impl LegalMoves for Race {
+   fn legal_inputs(&self, _pending : &Seat) -> impl Iterator<Item = u32> {
+      1..=3
+   }
+}
+
+impl Seated for Race {
+   fn seats_to_answer(&self, prompt : &<Self as Play>::Event) -> Seats {
+      (*prompt).into()
+   }
+}
+
+impl Determinize for Race {
+   fn determinize(&mut self, _observer : Seat, rng : &mut Rng) {
+      // Races have no hidden information, but make sure determinization actually happens.
+      rng.next_u64();
+      DETERMINIZED.with(|count| count.set(count.get() + 1));
+   }
+}
+
+thread_local! {
+   static DETERMINIZED : std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// The outcome of a [`Race`] is the winning seat, and the other seat (there are only two) comes second.
+impl Ranked for Seat {
+   fn standings(&self) -> Standings {
+      Standings::win(*self, [Seat(1 - self.0)])
+   }
+}
+
+#[test]
+fn test_session_tracks_pending_event() {
+   use crate::{host::PlayError, session::SessionError};
+
+   let host = Host::new(RoShamBo(Choice::Paper, Choice::Rock));
+   let mut session = Session::new(host.clone()).unwrap();
+   assert!(matches!(host.play(), Err(PlayError::AlreadyStarted)));
+
+   assert_eq!(session.pending(), Some(&Msg("Ro!".into())));
+   assert_eq!(session.resume(()).unwrap(), GeneratorState::Yielded(&Msg("Sham!".into())));
+   assert_eq!(session.resume(()).unwrap(), GeneratorState::Yielded(&Msg("Bo!".into())));
+   assert_eq!(session.resume(()).unwrap(), GeneratorState::Yielded(&Msg("Paper beats Rock.".into())));
+   assert_eq!(session.resume(()).unwrap(), GeneratorState::Complete(&Outcome::Win));
+   assert!(matches!(session.resume(()), Err(SessionError::Complete)));
+   assert_eq!(session.into_outcome(), Some(Outcome::Win));
+}
+
+#[test]
+fn test_lockstep_peers_stay_in_sync() {
+   use crate::lockstep::{Lockstep, LockstepError};
+
+   let (a_end, b_end) = MemoryTransport::pair();
+
+   let mut a = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(0));
+   a.add_peer(Seat(1), a_end);
+   let mut b = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(1));
+   b.add_peer(Seat(0), b_end);
+
+   assert!(matches!(b.submit(3), Err(LockstepError::NotYourTurn { owner: Seat(0) })));
+
+   // Each peer waits for the other's input before moving on.
+   let moves = [3, 1, 2, 2, 3];
+   for (turn, amount) in moves.into_iter().enumerate() {
+      let (local, remote) = if turn % 2 == 0 { (&mut a, &mut b) } else { (&mut b, &mut a) };
+      assert!(local.is_local_turn());
+      assert_eq!(remote.poll().unwrap(), 0);
+
+      local.submit(amount).unwrap();
+      assert_eq!(remote.poll().unwrap(), 1);
+      assert_eq!(local.turn(), remote.turn());
+   }
+
+   assert_eq!(a.session().outcome(), Some(&Seat(0)));
+   assert_eq!(b.session().outcome(), Some(&Seat(0)));
+   assert_eq!(a.session().host().clone_game(), b.session().host().clone_game());
+}
+
+#[test]
+fn test_lockstep_rejected_inputs_are_not_sent() {
+   use crate::{lockstep::{Lockstep, LockstepError}, session::SessionError};
+
+   let (a_end, b_end) = MemoryTransport::pair();
+
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   session.validate_inputs();
+   let mut a = Lockstep::new(session, Seat(0));
+   a.add_peer(Seat(1), a_end);
+   let mut b = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(1));
+   b.add_peer(Seat(0), b_end);
+
+   // The local session rejects the input, so the peer never hears about it.
+   assert!(matches!(a.submit(7), Err(LockstepError::Session(SessionError::IllegalInput))));
+   assert_eq!(b.poll().unwrap(), 0);
+   assert_eq!((a.turn(), b.turn()), (0, 0));
+   assert_eq!(b.session().host().borrow_game().total, 0);
+
+   a.submit(2).unwrap();
+   assert_eq!(b.poll().unwrap(), 1);
+   assert_eq!(b.session().host().borrow_game().total, 2);
+}
+
+#[test]
+fn test_lockstep_keeps_the_owners_input_when_another_peer_sends_one() {
+   use crate::lockstep::{Lockstep, LockstepError};
+
+   let (mut owner, owner_end) = MemoryTransport::pair();
+   let (mut intruder, intruder_end) = MemoryTransport::pair();
+   let mut b = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(1));
+   b.add_peer(Seat(0), owner_end);
+   b.add_peer(Seat(2), intruder_end);
+
+   // Seat 2 doesn't own the first turn, so its input is reported, and the owner's input survives to be played.
+   owner.send(&(0u64, 1u32).to_bytes()).unwrap();
+   intruder.send(&(0u64, 3u32).to_bytes()).unwrap();
+   assert!(matches!(b.poll(), Err(LockstepError::UnexpectedInput { turn: 0, seat: Seat(2) })));
+   assert_eq!(b.poll().unwrap(), 1);
+   assert_eq!(b.session().host().borrow_game().total, 1);
+}
+
+#[test]
+fn test_lockstep_sends_to_every_reachable_peer() {
+   use crate::lockstep::{Lockstep, LockstepError};
+
+   let (a_end, b_end) = MemoryTransport::pair();
+   let (a_gone, _) = MemoryTransport::pair();
+
+   let mut a = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(0));
+   a.add_peer(Seat(2), a_gone);
+   a.add_peer(Seat(1), a_end);
+   let mut b = Lockstep::new(Session::new(Host::new(Race::default())).unwrap(), Seat(1));
+   b.add_peer(Seat(0), b_end);
+
+   // The unreachable peer is dropped, but the turn is played, and the peer after it still hears about it.
+   let Err(LockstepError::Unreachable(disconnected)) = a.submit(2) else { panic!("expected an unreachable peer") };
+   assert_eq!(disconnected.iter().map(|peer| peer.seat).collect::<Vec<_>>(), [Seat(2)]);
+   assert_eq!(a.turn(), 1);
+   assert_eq!(b.poll().unwrap(), 1);
+
+   b.submit(3).unwrap();
+   assert_eq!(a.poll().unwrap(), 1);
+   assert_eq!(a.session().host().borrow_game().total, 5);
+}
+
+#[test]
+fn test_predictions_are_confirmed_or_rolled_back() {
+   let (server_end, client_end) = MemoryTransport::pair();
+   let host = Host::new(Tally::default());
+   let mut server = Replicator::new(host.clone(), server_end);
+   let mut client = Predictor::new(Mirror::new(Host::new(Tally::default()), client_end));
+
+   // The client sees the result of its move straight away, and the server agrees.
+   assert_eq!(client.predict(&()), vec![1]);
+   assert_eq!(client.view().borrow_game().total, 1);
+   assert!(client.is_predicting());
+
+   assert!(host.process_event(&mut 1));
+   server.publish(&1).unwrap();
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: false, events: vec![] });
+   assert!(!client.is_predicting());
+
+   // This time, the server disagrees with both predictions.
+   assert_eq!(client.predict(&()), vec![2]);
+   assert_eq!(client.predict(&()), vec![3]);
+   assert_eq!(client.view().borrow_game().total, 6);
+
+   for mut event in [5, 6] {
+      assert!(host.process_event(&mut event));
+      server.publish(&event).unwrap();
+   }
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: true, events: vec![5, 6] });
+   assert_eq!(client.rollbacks(), 1);
+   assert!(!client.is_predicting());
+   assert_eq!(client.view().clone_game(), host.clone_game());
+   assert_eq!(client.mirror().host().clone_game(), host.clone_game());
+
+   // Events nobody predicted are passed straight through.
+   assert!(host.process_event(&mut 4));
+   server.publish(&4).unwrap();
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: false, events: vec![4] });
+   assert_eq!(client.view().clone_game(), host.clone_game());
+}
+
+#[test]
+fn test_predictions_are_rolled_back_by_unrequested_snapshots() {
+   let (server_end, client_end) = MemoryTransport::pair();
+   let host = Host::new(Tally::default());
+   let mut server = Replicator::new(host.clone(), server_end);
+   let mut client = Predictor::new(Mirror::new(Host::new(Tally::default()), client_end));
+
+   // The snapshot arrives without the client asking for one, so the mirror never looks like it is resyncing.
+   assert_eq!(client.predict(&()), vec![1]);
+   host.borrow_game_mut().total = 10;
+   server.send_snapshot().unwrap();
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: true, events: vec![] });
+   assert_eq!(client.mirror().snapshots(), 1);
+   assert!(!client.is_predicting());
+   assert_eq!(client.view().clone_game(), host.clone_game());
+}
+
+#[test]
+fn test_predictions_only_pass_through_the_view_middleware_once() {
+   use crate::middleware::Intercept;
+
+   let (server_end, client_end) = MemoryTransport::pair();
+   let host = Host::new(Tally::default());
+   let mut server = Replicator::new(host.clone(), server_end);
+   let mut client = Predictor::new(Mirror::new(Host::new(Tally::default()), client_end));
+   client.view().push_middleware(|_ : &Tally, event : &mut u32| {
+      *event *= 10;
+      Intercept::Continue
+   });
+
+   // The predicted event is scaled by the view's middleware, but the authoritative events are applied as they are.
+   assert_eq!(client.predict(&()), vec![10]);
+   for mut event in [2, 3] {
+      assert!(host.process_event(&mut event));
+      server.publish(&event).unwrap();
+   }
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: true, events: vec![2, 3] });
+   assert_eq!(client.view().clone_game(), host.clone_game());
+
+   assert!(host.process_event(&mut 4));
+   server.publish(&4).unwrap();
+   assert_eq!(client.poll().unwrap(), Reconciliation { rolled_back: false, events: vec![4] });
+   assert_eq!(client.view().clone_game(), host.clone_game());
+}
+
+/// A card dealt to one of the players in a game of [`Hands`]. The card is [`None`] if it was dealt face-down.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Dealt(Seat, Option<u8>);
+
+impl Wire for Dealt {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.0.encode(out);
+      self.1.encode(out);
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      Ok(Self(Wire::decode(input)?, Wire::decode(input)?))
+   }
+}
+
+impl Redact for Dealt {
+   type View = Dealt;
+
+   fn view_for(&self, seat : Seat) -> Option<Self::View> {
+      // Players only see their own cards, and spectators don't even see who was dealt a card.
+      match seat {
+         Seat::SPECTATOR => None,
+         seat if seat == self.0 => Some(self.clone()),
+         _ => Some(Dealt(self.0, None)),
+      }
+   }
+}
+
+/// A game in which two players are each dealt a card, which only they can see.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Hands(Vec<Option<u8>>, Vec<Option<u8>>);
+
+impl Wire for Hands {
+   fn encode(&self, out : &mut Vec<u8>) {
+      self.0.encode(out);
+      self.1.encode(out);
+   }
+
+   fn decode(input : &mut &[u8]) -> Result<Self, WireError> {
+      Ok(Self(Wire::decode(input)?, Wire::decode(input)?))
+   }
+}
+
+impl Redact for Hands {
+   type View = Hands;
+
+   fn view_for(&self, seat : Seat) -> Option<Self::View> {
+      let hide = |hand : &Vec<Option<u8>>| hand.iter().map(|_| None).collect();
+      match seat {
+         Seat(0) => Some(Hands(self.0.clone(), hide(&self.1))),
+         Seat(1) => Some(Hands(hide(&self.0), self.1.clone())),
+         Seat::SPECTATOR => Some(Hands(hide(&self.0), hide(&self.1))),
+
+         // Nobody else may even look at the table.
+         _ => None,
+      }
+   }
+}
+
+impl Play for Hands {
+   type Input = ();
+   type Event = Dealt;
+   type Outcome = ();
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      ctx.yield_event(Dealt(Seat(0), Some(7))).await;
+      ctx.yield_event(Dealt(Seat(1), Some(3))).await;
+   }
+
+   fn handle_event(&mut self, event : &mut <Self as Play>::Event) {
+      let Dealt(seat, card) = event;
+      let hand = if *seat == Seat(0) { &mut self.0 } else { &mut self.1 };
+      hand.push(*card);
+   }
+}
+
+#[test]
+fn test_broadcaster_redacts_events_per_seat() {
+   let host = Host::new(Hands::default());
+   let mut server = Broadcaster::new(host.clone());
+
+   // Everyone joins before the cards are dealt, except the spectator, who catches up from a snapshot.
+   let mut mirrors = Vec::new();
+   for seat in [Seat(0), Seat(1)] {
+      let (server_end, client_end) = MemoryTransport::pair();
+      server.subscribe(seat, server_end).unwrap();
+      mirrors.push(Mirror::new(Host::new(Hands::default()), client_end));
+   }
+
+   let mut session = Session::new(host).unwrap();
+   while let Some(event) = session.pending() {
+      assert!(server.publish(event).is_empty());
+      session.resume(()).unwrap();
+   }
+
+   let (server_end, client_end) = MemoryTransport::pair();
+   server.subscribe(Seat::SPECTATOR, server_end).unwrap();
+   mirrors.push(Mirror::new(Host::new(Hands::default()), client_end));
+
+   assert_eq!(mirrors[0].poll().unwrap(), vec![Dealt(Seat(0), Some(7)), Dealt(Seat(1), None)]);
+   assert_eq!(mirrors[1].poll().unwrap(), vec![Dealt(Seat(0), None), Dealt(Seat(1), Some(3))]);
+   assert_eq!(mirrors[2].poll().unwrap(), vec![]);
+
+   // The full game state is still known to the server.
+   assert_eq!(server.host().clone_game(), Hands(vec![Some(7)], vec![Some(3)]));
+   assert_eq!(mirrors[0].host().clone_game(), Hands(vec![Some(7)], vec![None]));
+   assert_eq!(mirrors[1].host().clone_game(), Hands(vec![None], vec![Some(3)]));
+   assert_eq!(mirrors[2].host().clone_game(), Hands(vec![None], vec![None]));
+}
+
+#[test]
+fn test_broadcaster_drops_unreachable_subscribers() {
+   let host = Host::new(Hands::default());
+   let mut server = Broadcaster::new(host.clone());
+
+   let mut clients = Vec::new();
+   for seat in [Seat(0), Seat(1), Seat(2)] {
+      let (server_end, client_end) = MemoryTransport::pair();
+      server.subscribe(seat, server_end).unwrap();
+      clients.push(Mirror::new(Host::new(Hands::default()), client_end));
+   }
+
+   // Seat 1 hangs up, but everyone else still receives the event.
+   clients.remove(1);
+   let disconnected = server.publish(&Dealt(Seat(0), Some(7)));
+   assert_eq!(disconnected.iter().map(|client| client.seat).collect::<Vec<_>>(), [Seat(1)]);
+   assert_eq!(server.seats().collect::<Vec<_>>(), [Seat(0), Seat(2)]);
+   assert_eq!(clients[0].poll().unwrap(), vec![Dealt(Seat(0), Some(7))]);
+   assert!(server.publish(&Dealt(Seat(1), Some(3))).is_empty());
+
+   // Seats that can't see the game still get a snapshot when they ask for one, so they don't wait forever.
+   clients[1].poll().unwrap();
+   clients[1].request_resync().unwrap();
+   assert!(server.poll().is_empty());
+   clients[1].poll().unwrap();
+   assert!(!clients[1].is_resyncing());
+
+   // The empty snapshot leaves what the seat has already seen alone.
+   assert_eq!(clients[1].host().clone_game(), Hands(vec![None], vec![None]));
+}
+
+#[test]
+fn test_session_rejects_inputs_from_wrong_seat() {
+   use crate::session::SessionError;
+
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   assert_eq!(session.seats_to_answer(), Seats::from(Seat(0)));
+
+   let err = session.resume_as(Seat(1), 3).unwrap_err();
+   assert_eq!(err, SessionError::WrongSeat { seat: Seat(1), expected: Seat(0).into() });
+   assert_eq!(session.host().borrow_game().total, 0);
+
+   assert_eq!(session.resume_as(Seat(0), 3).unwrap(), GeneratorState::Yielded(&Seat(1)));
+   assert!(session.resume_as(Seat(0), 3).is_err());
+   assert_eq!(session.resume_as(Seat(1), 2).unwrap(), GeneratorState::Yielded(&Seat(0)));
+   assert_eq!(session.host().borrow_game().total, 5);
+}
+
+#[test]
+fn test_seats_behave_like_a_set() {
+   let mut seats : Seats = [Seat(3), Seat(0), Seat(63)].into_iter().collect();
+   assert_eq!(seats.len(), 3);
+   assert!(!seats.insert(Seat(3)));
+   assert!(seats.remove(Seat(3)));
+   assert!(!seats.contains(Seat(3)));
+   assert!(!seats.contains(Seat::SPECTATOR));
+   assert_eq!(seats.iter().collect::<Vec<_>>(), vec![Seat(0), Seat(63)]);
+   assert_eq!(Seats::first_n(3).iter().collect::<Vec<_>>(), vec![Seat(0), Seat(1), Seat(2)]);
+   assert_eq!(format!("{seats:?}"), "{Seat(0), Seat(63)}");
+}
+
+/// A sealed-bid auction between two bidders. The highest bid wins, and ties go to nobody.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Auction {
+   bids : Vec<(Seat, u32)>,
+}
+
+impl Play for Auction {
+   type Input = u32;
+   type Event = String;
+   type Outcome = Option<Seat>;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      let bids = ctx.ask_all(Seats::first_n(2), "Place your bids!".into()).await;
+      let mut bids : Vec<_> = bids.into_iter().collect();
+      bids.sort();
+      ctx.host.borrow_game_mut().bids = bids.clone();
+
+      let (&(first, a), &(second, b)) = (&bids[0], &bids[1]);
+      match a.cmp(&b) {
+         Ordering::Greater => Some(first),
+         Ordering::Less => Some(second),
+         Ordering::Equal => None,
+      }
+   }
+}
+
+impl Seated for Auction {
+   fn seats_to_answer(&self, _prompt : &<Self as Play>::Event) -> Seats {
+      Seats::EMPTY
+   }
+}
+
+#[test]
+fn test_ask_all_waits_for_every_seat() {
+   use crate::session::SessionError;
+
+   let mut session = Session::new(Host::new(Auction::default())).unwrap();
+   assert_eq!(session.seats_to_answer(), Seats::first_n(2));
+   assert_eq!(session.resume(5), Err(SessionError::SeatRequired));
+
+   assert_eq!(session.resume_as(Seat(1), 7).unwrap(), GeneratorState::Yielded(&"Place your bids!".to_string()));
+   assert!(session.host().borrow_game().bids.is_empty());
+   assert_eq!(session.seats_to_answer(), Seat(0).into());
+   assert_eq!(session.resume_as(Seat(1), 9), Err(SessionError::AlreadySubmitted { seat: Seat(1) }));
+   assert!(matches!(session.resume_as(Seat(2), 9), Err(SessionError::WrongSeat { seat: Seat(2), .. })));
+
+   assert_eq!(session.resume_as(Seat(0), 4).unwrap(), GeneratorState::Complete(&Some(Seat(1))));
+   assert_eq!(session.host().borrow_game().bids, vec![(Seat(0), 4), (Seat(1), 7)]);
+}
+
+#[test]
+fn test_lockstep_answers_ask_all_one_seat_at_a_time() {
+   use crate::lockstep::{Lockstep, LockstepError};
+
+   let (a_end, b_end) = MemoryTransport::pair();
+   let mut a = Lockstep::new(Session::new(Host::new(Auction::default())).unwrap(), Seat(0));
+   a.add_peer(Seat(1), a_end);
+   let mut b = Lockstep::new(Session::new(Host::new(Auction::default())).unwrap(), Seat(1));
+   b.add_peer(Seat(0), b_end);
+
+   // Both seats were asked, but the lowest seat answers first.
+   assert!(matches!(b.submit(7), Err(LockstepError::NotYourTurn { owner: Seat(0) })));
+   a.submit(4).unwrap();
+   assert_eq!(b.poll().unwrap(), 1);
+
+   b.submit(7).unwrap();
+   assert_eq!(a.poll().unwrap(), 1);
+   assert_eq!(a.session().outcome(), Some(&Some(Seat(1))));
+   assert_eq!(b.session().outcome(), Some(&Some(Seat(1))));
+}
+
+#[test]
+fn test_turn_order_handles_skips_reversals_and_extra_turns() {
+   let mut turns = TurnOrder::new((0..4).map(Seat));
+   assert_eq!(turns.advance(), Some(&Seat(0)));
+   turns.skip(1);
+   assert_eq!(turns.advance(), Some(&Seat(2)));
+   turns.reverse();
+   assert_eq!(turns.advance(), Some(&Seat(1)));
+   turns.grant_extra_turn();
+   assert_eq!(turns.advance(), Some(&Seat(1)));
+   assert_eq!(turns.advance(), Some(&Seat(0)));
+   assert_eq!(turns.advance(), Some(&Seat(3)));
+
+   assert!(turns.eliminate(&Seat(3)));
+   assert!(!turns.eliminate(&Seat(3)));
+   assert_eq!(turns.current(), None);
+   assert_eq!(turns.advance(), Some(&Seat(2)));
+   turns.reverse();
+   assert!(turns.eliminate(&Seat(2)));
+   assert_eq!(turns.advance(), Some(&Seat(0)));
+   assert_eq!(turns.players().collect::<Vec<_>>(), vec![&Seat(0), &Seat(1)]);
+}
+
+/// A game of "hot potato" where each player passes or holds the potato on their turn. Holding it twice in a row
+/// eliminates the player, and the last player standing wins.
+#[derive(Clone)]
+struct HotPotato {
+   turns : TurnOrder,
+   holding : Option<Seat>,
+}
+
+impl Play for HotPotato {
+   type Input = bool;
+   type Event = Seat;
+   type Outcome = Option<Seat>;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      while let Some((seat, hold)) = ctx.next_turn().await {
+         let mut game = ctx.host.borrow_game_mut();
+         if !hold {
+            game.holding = None;
+         }
+         else if game.holding.replace(seat) == Some(seat) {
+            game.turns.eliminate(&seat);
+            if game.turns.len() == 1 {
+               return game.turns.players().next().copied();
+            }
+         }
+         else {
+            game.turns.grant_extra_turn();
+         }
+      }
+
+      None
+   }
+}
+
+impl TakeTurns for HotPotato {
+   type Player = Seat;
+
+   fn turn_order(&mut self) -> &mut TurnOrder {
+      &mut self.turns
+   }
+
+   fn turn_changed(&self, player : &Seat) -> Self::Event {
+      *player
+   }
+}
+
+#[test]
+fn test_next_turn_yields_turn_changes() {
+   let mut session = Session::new(Host::new(HotPotato { turns: TurnOrder::new((0..3).map(Seat)), holding: None }))
+      .unwrap();
+
+   assert_eq!(session.pending(), Some(&Seat(0)));
+   assert_eq!(session.resume(false).unwrap(), GeneratorState::Yielded(&Seat(1)));
+   assert_eq!(session.resume(true).unwrap(), GeneratorState::Yielded(&Seat(1)));
+   assert_eq!(session.resume(true).unwrap(), GeneratorState::Yielded(&Seat(2)));
+   assert_eq!(session.host().borrow_game().turns.len(), 2);
+   assert_eq!(session.resume(true).unwrap(), GeneratorState::Yielded(&Seat(2)));
+   assert_eq!(session.resume(true).unwrap(), GeneratorState::Complete(&Some(Seat(0))));
+}
+
+#[test]
+fn test_next_turn_picks_up_the_same_turn_after_a_restart() {
+   let mut session = Session::new(Host::new(HotPotato { turns: TurnOrder::new((0..3).map(Seat)), holding: None }))
+      .unwrap();
+   assert_eq!(session.resume(false).unwrap(), GeneratorState::Yielded(&Seat(1)));
+
+   // A copy taken while seat 1 is deciding starts over on seat 1's turn, and plays on just like the original.
+   let mut copy = Session::new(Host::new(session.host().clone_game())).unwrap();
+   assert_eq!(copy.pending(), Some(&Seat(1)));
+   assert_eq!(copy.resume(false).unwrap(), GeneratorState::Yielded(&Seat(2)));
+   assert_eq!(session.resume(false).unwrap(), GeneratorState::Yielded(&Seat(2)));
+}
+
+#[test]
+fn test_standings_rank_ties_and_eliminations() {
+   use crate::Ranked;
+
+   let standings = Standings::from_scores([(Seat(0), 5), (Seat(1), 9), (Seat(2), 5), (Seat(3), 1)]);
+   let places : Vec<_> = standings.iter().map(|placement| (placement.player, placement.place)).collect();
+   assert_eq!(places, vec![(Seat(1), 1), (Seat(0), 2), (Seat(2), 2), (Seat(3), 4)]);
+   assert_eq!(standings.compare(&Seat(0), &Seat(3)), Some(Ordering::Greater));
+   assert_eq!(standings.compare(&Seat(0), &Seat(2)), Some(Ordering::Equal));
+   assert_eq!(standings.winners().collect::<Vec<_>>(), vec![&Seat(1)]);
+
+   let standings = Standings::from_eliminations([Seat(2)], [Seat(0), Seat(1)]);
+   assert_eq!(standings.place_of(&Seat(0)), Some(3));
+   assert!(standings.get(&Seat(1)).unwrap().is_eliminated);
+   assert!(!standings.is_draw());
+   assert!(Standings::draw([Seat(0), Seat(1)]).standings().is_draw());
+}
+
+#[test]
+fn test_agents_answer_prompts_for_bound_seats() {
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   session.bind(Seat(1), |_ : &Race, _ : &Seat| 3);
+   assert!(session.is_bound(Seat(1)));
+
+   // The agent answers right after each human move, so only prompts for the human seat are ever seen.
+   assert_eq!(session.resume_as(Seat(0), 1).unwrap(), GeneratorState::Yielded(&Seat(0)));
+   assert_eq!(session.host().borrow_game().total, 4);
+   assert_eq!(session.resume_as(Seat(0), 1).unwrap(), GeneratorState::Yielded(&Seat(0)));
+   assert_eq!(session.resume_as(Seat(0), 2).unwrap(), GeneratorState::Complete(&Seat(0)));
+
+   let mut session = Session::new(Host::new(Auction::default())).unwrap();
+   session.bind(Seat(0), |_ : &Auction, _ : &String| 5);
+   assert_eq!(session.seats_to_answer(), Seat(1).into());
+   assert_eq!(session.resume_as(Seat(1), 2).unwrap(), GeneratorState::Complete(&Some(Seat(0))));
+}
+
+#[test]
+fn test_session_rejects_illegal_inputs() {
+   use crate::session::SessionError;
+
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   assert_eq!(session.legal_inputs(), vec![1, 2, 3]);
+   assert!(!session.is_legal(&4));
+
+   // Inputs are only checked once validation is enabled.
+   session.resume(7).unwrap();
+   assert_eq!(session.host().borrow_game().total, 3);
+
+   session.validate_inputs();
+   assert_eq!(session.resume_as(Seat(1), 7), Err(SessionError::IllegalInput));
+   assert_eq!(session.resume_as(Seat(1), 2).unwrap(), GeneratorState::Yielded(&Seat(0)));
+   assert_eq!(session.host().borrow_game().total, 5);
+}
+
+#[test]
+fn test_sessions_can_require_a_seat_for_seated_prompts() {
+   use crate::session::SessionError;
+
+   // Without seats being required, any input is accepted.
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   session.resume(2).unwrap();
+
+   session.require_seats();
+   assert_eq!(session.resume(2), Err(SessionError::SeatRequired));
+   assert_eq!(session.host().borrow_game().total, 2);
+   assert_eq!(session.resume_as(Seat(1), 2).unwrap(), GeneratorState::Yielded(&Seat(0)));
+}
+
+#[test]
+fn test_minimax_finds_winning_moves() {
+   use crate::search::{minimax, MinimaxAgent};
+
+   fn eval(session : &Session<Race>, seat : Seat) -> i32 {
+      match session.outcome() {
+         Some(winner) if *winner == seat => 1,
+         Some(_) => -1,
+         None => 0,
+      }
+   }
+
+   // Leaving the total at 2 or 6 means the other player can never win.
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   let best = minimax(&session, Seat(0), 10, eval).unwrap();
+   assert_eq!((best.input, best.score), (2, 1));
+
+   session.bind(Seat(1), MinimaxAgent::new(10, eval));
+   session.resume_as(Seat(0), 1).unwrap();
+   assert_eq!(session.host().borrow_game().total, 2);
+}
+
+/// A [`Clock`] that advances by one millisecond every time it is read.
+#[derive(Default)]
+struct TickingClock(std::cell::Cell<u64>);
+
+impl Clock for TickingClock {
+   fn now(&self) -> std::time::Duration {
+      let ticks = self.0.get();
+      self.0.set(ticks + 1);
+      std::time::Duration::from_millis(ticks)
+   }
+}
+
+#[test]
+fn test_mcts_finds_winning_moves() {
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   let mut mcts = Mcts::new(Budget::Iterations(2000), 7);
+   let best = mcts.search(&session, Seat(0)).unwrap();
+   assert_eq!((best.input, best.iterations), (2, 2000));
+   assert!(best.mean_reward > 0.5);
+
+   session.bind(Seat(1), Mcts::new(Budget::Iterations(2000), 7));
+   session.resume_as(Seat(0), 3).unwrap();
+   assert_eq!(session.host().borrow_game().total, 6);
+
+   // Even an empty budget plays out one game, so there is always a move to choose.
+   assert_eq!(Mcts::new(Budget::Iterations(0), 7).search(&session, Seat(0)).unwrap().iterations, 1);
+}
+
+#[test]
+fn test_mcts_respects_time_budget() {
+   let session = Session::new(Host::new(Race::default())).unwrap();
+   let mut mcts = Mcts::new(Budget::Time(std::time::Duration::from_millis(10)), 7)
+      .with_clock(TickingClock::default())
+      .determinized();
+
+   // The clock is read once at the start, then before every iteration after the first.
+   let best = mcts.search(&session, Seat(0)).unwrap();
+   assert_eq!(best.iterations, 10);
+   assert_eq!(DETERMINIZED.with(|count| count.get()), 10);
+}
+
+/// Seat 0 must guess a secret number between `0` and `2`, and is only allowed to guess right. Seat 0 is never told the
+/// secret, so a determinized search has to consider every possible secret.
+#[derive(Clone, Debug, Default)]
+struct Guess {
+   secret : u32,
+}
+
+impl Play for Guess {
+   type Input = u32;
+   type Event = Seat;
+   type Outcome = Seat;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      let guess = ctx.yield_event(Seat(0)).await;
+      if guess == ctx.host.borrow_game().secret { Seat(0) } else { Seat(1) }
+   }
+}
+
+impl LegalMoves for Guess {
+   fn legal_inputs(&self, _pending : &Seat) -> impl Iterator<Item = u32> {
+      [self.secret].into_iter()
+   }
+}
+
+impl Seated for Guess {
+   fn seats_to_answer(&self, prompt : &<Self as Play>::Event) -> Seats {
+      (*prompt).into()
+   }
+}
+
+impl Determinize for Guess {
+   fn determinize(&mut self, _observer : Seat, rng : &mut Rng) {
+      self.secret = rng.below(3) as u32;
+   }
+}
+
+#[test]
+fn test_mcts_only_follows_moves_legal_in_each_determinization() {
+   let session = Session::new(Host::new(Guess { secret: 1 })).unwrap();
+   let mut mcts = Mcts::new(Budget::Iterations(30), 7).determinized();
+
+   // Every secret gets its own move, which always wins whenever it is followed.
+   let best = mcts.search(&session, Seat(0)).unwrap();
+   assert!(best.visits < best.iterations);
+   assert_eq!(best.mean_reward, 1.0);
+}
+
+/// A coin toss announced to nobody in particular, whose input decides the winner. This stands in for chance events
+/// (e.g. dice rolls) that no seat has to answer, but whose every outcome a search still has to try.
+#[derive(Clone, Debug, Default)]
+struct Coin;
+
+impl Play for Coin {
+   type Input = u8;
+   type Event = ();
+   type Outcome = Seat;
+
+   async fn play(ctx : Context<Self>) -> Self::Outcome {
+      Seat(ctx.yield_event(()).await)
+   }
+}
+
+impl LegalMoves for Coin {
+   fn legal_inputs(&self, _pending : &()) -> impl Iterator<Item = u8> {
+      0..2
+   }
+}
+
+impl Seated for Coin {
+   fn seats_to_answer(&self, _prompt : &<Self as Play>::Event) -> Seats {
+      Seats::EMPTY
+   }
+}
+
+#[test]
+fn test_mcts_credits_unseated_prompts_to_the_last_mover() {
+   let session = Session::new(Host::new(Coin)).unwrap();
+   let mut mcts = Mcts::new(Budget::Iterations(50), 7);
+
+   // Nobody has moved yet, so the toss is judged from the point of view of the searching seat.
+   let best = mcts.search(&session, Seat(0)).unwrap();
+   assert_eq!((best.input, best.mean_reward), (0, 1.0));
+}
+
+#[test]
+fn test_forked_sessions_diverge_independently() {
+   let mut session = Session::recorded(Host::new(Race::default())).unwrap();
+   assert!(Session::new(Host::new(Race::default())).unwrap().fork().is_none());
+
+   session.resume(2).unwrap();
+   session.resume(1).unwrap();
+   let mut fork = session.fork().unwrap();
+   assert!(fork.is_recorded());
+   assert_eq!(fork.pending(), session.pending());
+   assert_eq!(*fork.host().borrow_game(), *session.host().borrow_game());
+
+   // Each session can now play out a different game.
+   session.resume(3).unwrap();
+   fork.resume(1).unwrap();
+   assert_eq!(session.host().borrow_game().total, 6);
+   assert_eq!(fork.host().borrow_game().total, 4);
+   assert_eq!(session.pending(), Some(&Seat(1)));
+   assert_eq!(fork.pending(), Some(&Seat(1)));
+
+   // Forks of forks replay the whole game, including the moves made before the first fork.
+   let mut again = fork.fork().unwrap();
+   assert_eq!(again.resume(3).unwrap(), GeneratorState::Yielded(&Seat(0)));
+   assert_eq!(again.host().borrow_game().total, 7);
+   assert_eq!(fork.host().borrow_game().total, 4);
+
+   // Forks require seats just like the original.
+   again.require_seats();
+   assert_eq!(again.fork().unwrap().resume(1), Err(crate::session::SessionError::SeatRequired));
+}
+
+#[test]
+fn test_simulations_are_reproducible_across_threads() {
+   use crate::simulate::Simulation;
+
+   // Seat 1 always adds 3, while seat 0 plays randomly.
+   let setup = |_seed| {
+      let mut session = Session::new(Host::new(Race::default())).unwrap();
+      session.bind(Seat(1), |_ : &Race, _ : &Seat| 3);
+      session
+   };
+
+   let mut simulation = Simulation::new(500, setup);
+   simulation.set_seed(42);
+   let report = simulation.run();
+   simulation.set_threads(4);
+   assert_eq!(simulation.run(), report);
+
+   assert_eq!((report.games, report.unfinished, report.draws), (500, 0, 0));
+   let (random, greedy) = (&report.seats[&Seat(0)], &report.seats[&Seat(1)]);
+   assert_eq!(random.wins + greedy.wins, 500);
+   assert!(greedy.win_rate() > random.win_rate());
+   assert!(report.min_events >= 4 && report.max_events <= 10);
+   assert!(report.to_csv().starts_with("seat,games,wins,draws,losses,win_rate,average_place\n0,500,"));
+   assert!(report.to_json().starts_with("{\"games\":500,\"unfinished\":0,\"draws\":0,"));
+}
+
+#[test]
+fn test_tournaments_rate_stronger_agents_higher() {
+   use crate::{search::MinimaxAgent, tournament::{Format, Tournament}};
+
+   fn eval(session : &Session<Race>, seat : Seat) -> i32 {
+      session.outcome().map_or(0, |winner| if *winner == seat { 1 } else { -1 })
+   }
+
+   let setup = |_seed| Session::new(Host::new(Race::default())).unwrap();
+   let mut tournament = Tournament::new(Format::RoundRobin(2), setup);
+   tournament.register("ones", |_seed| |_ : &Race, _ : &Seat| 1);
+   tournament.register("threes", |_seed| |_ : &Race, _ : &Seat| 3);
+   tournament.register("minimax", |_seed| MinimaxAgent::new(10, eval));
+   tournament.set_seed(3);
+
+   let results = tournament.run();
+   assert_eq!(tournament.run(), results);
+   assert_eq!(results.games.len(), 2 * 3 * 2);
+   assert!(results.games.iter().all(|game| game.is_finished));
+   assert_eq!(results.leaderboard()[0].name, "minimax");
+   assert_eq!(results.entrants[2].losses, 0);
+
+   // Seats alternate within every match, and each entrant's agent gets a seed of its own.
+   assert_eq!((results.games[0].seats, results.games[1].seats), ([0, 1], [1, 0]));
+   assert!(results.games.iter().all(|game| game.agent_seeds[0] != game.agent_seeds[1]));
+
+   // In a Swiss tournament with an odd number of entrants, everyone sits out once before anyone sits out twice.
+   let mut swiss = Tournament::new(Format::Swiss(3), setup);
+   for name in ["a", "b", "c"] {
+      swiss.register(name, |_seed| |_ : &Race, _ : &Seat| 2);
+   }
+   let results = swiss.run();
+   assert_eq!(results.games.len(), 3 * 2);
+   assert!(results.entrants.iter().all(|entrant| entrant.wins + entrant.draws + entrant.losses == 4));
+}
+
+#[test]
+fn test_scripts_play_games_step_by_step() {
+   use crate::testing::Script;
+
+   let script = Script::new(Host::new(RoShamBo(Choice::Paper, Choice::Rock)))
+      .expect_event(Msg("Ro!".into()))
+      .send(())
+      .expect_event(Msg("Sham!".into()))
+      .send(())
+      .send(())
+      .expect_event(Msg("Paper beats Rock.".into()))
+      .send(())
+      .expect_outcome(Outcome::Win);
+   assert_eq!(script.transcript().lines().count(), 9);
+   assert_eq!(script.transcript().lines().last(), Some("outcome: Win"));
+
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   session.bind(Seat(1), |_ : &Race, _ : &Seat| 3);
+   Script::from_session(session)
+      .send_as(Seat(0), 2)
+      .expect_state(|race| race.total == 5)
+      .send_as(Seat(0), 2)
+      .inspect(|race| assert_eq!(race.total, 10))
+      .expect_outcome(Seat(1));
+}
+
+#[test]
+#[should_panic(expected = "expected event: Msg(\"Bo!\")\n   found event: Msg(\"Sham!\")\n\ntranscript so far:\n   \
+   event: Msg(\"Ro!\")\n   input: ()\n   event: Msg(\"Sham!\")")]
+fn test_scripts_show_the_transcript_on_failure() {
+   crate::testing::Script::new(Host::new(RoShamBo(Choice::Paper, Choice::Rock)))
+      .send(())
+      .expect_event(Msg("Bo!".into()));
+}
+
+#[test]
+fn test_scripts_match_golden_transcripts() {
+   use crate::testing::{assert_snapshot, Script, BLESS_VAR};
+
+   let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/transcripts/ro_sham_bo.txt");
+   Script::new(Host::new(RoShamBo(Choice::Scissors, Choice::Rock)))
+      .send_all([(); 4])
+      .expect_outcome(Outcome::Loss)
+      .expect_transcript(path);
+
+   // Check the diff against a scratch copy, which blessing would overwrite.
+   if std::env::var_os(BLESS_VAR).is_none() {
+      let scratch = std::env::temp_dir().join(format!("posturn-{}-ro_sham_bo.txt", std::process::id()));
+      std::fs::copy(path, &scratch).unwrap();
+      let mismatch = std::panic::catch_unwind(|| assert_snapshot(&scratch, "event: Msg(\"Ro!\")\n")).unwrap_err();
+      std::fs::remove_file(&scratch).unwrap();
+      let message = mismatch.downcast_ref::<String>().unwrap();
+      assert!(message.contains("  event: Msg(\"Ro!\")\n- input: ()\n- event: Msg(\"Sham!\")\n"));
+   }
+}
+
+#[test]
+fn test_fuzzing_shrinks_failing_games() {
+   use crate::testing::{Fuzz, FailureKind};
+
+   let mut fuzz = Fuzz::legal(|_seed| Race::default());
+   fuzz.check("total never overshoots the goal", |race| race.total < Race::GOAL + 3);
+   fuzz.set_max_inputs(Race::GOAL as usize);
+   fuzz.assert();
+
+   // Every game eventually breaks this invariant, but only a few inputs are needed to reproduce it.
+   fuzz.check("total stays below 7", |race| race.total < 7);
+   fuzz.set_seed(5);
+   let failure = fuzz.run().unwrap_err();
+   assert_eq!(failure.kind, FailureKind::Invariant("total stays below 7".into()));
+   assert!(failure.inputs.iter().sum::<u32>() >= 7);
+   assert!(failure.inputs[1..].iter().sum::<u32>() < 7);
+   assert_eq!(failure.transcript.lines().count(), 2 * failure.inputs.len() + 1);
+   assert_eq!(fuzz.replay(failure.seed, &failure.inputs).0, Err(failure.kind));
+
+   // A game that never ends is reported too.
+   let mut fuzz = Fuzz::new(|_seed| Race::default(), |_ : &Race, _ : &Seat, _ : &mut Rng| 1);
+   fuzz.set_max_inputs(5);
+   assert_eq!(fuzz.run().unwrap_err().kind, FailureKind::Unfinished(5));
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_fuzzing_with_arbitrary_inputs() {
+   use crate::testing::{Fuzz, FailureKind};
+
+   // Races clamp every move, so even the most outlandish inputs can't overshoot the goal.
+   let mut fuzz = Fuzz::arbitrary(|_seed| Race::default());
+   fuzz.check("total never overshoots the goal", |race| race.total < Race::GOAL + 3);
+   fuzz.assert();
+
+   fuzz.check("total stays below 7", |race| race.total < 7);
+   let failure = fuzz.run().unwrap_err();
+   assert_eq!(failure.kind, FailureKind::Invariant("total stays below 7".into()));
+   assert_eq!(fuzz.replay(failure.seed, &failure.inputs).0, Err(failure.kind));
+}
+
+#[test]
+fn test_conformance_checks_find_broken_contracts() {
+   use crate::{session::SessionError, testing::{check_conformance, ConformanceError}};
+
+   check_conformance(Tally::default(), [(); 3]).unwrap();
+   check_conformance(RoShamBo(Choice::Rock, Choice::Paper), [(); 4]).unwrap();
+
+   // Races change their total outside of `handle_event`, so they can't be mirrored.
+   let error = check_conformance(Race::default(), [3, 3, 3, 3]).unwrap_err();
+   assert_eq!(error, ConformanceError::MirrorDiverged {
+      step: 1,
+      event: "Seat(1)".into(),
+      expected: "Race { total: 3, turn: Seat(1) }".into(),
+      actual: "Race { total: 0, turn: Seat(0) }".into(),
+   });
+
+   // Bids have to be placed seat by seat, which a plain list of inputs can't do.
+   let error = check_conformance(Auction::default(), [5, 7]).unwrap_err();
+   assert_eq!(error, ConformanceError::Rejected { step: 0, error: SessionError::SeatRequired });
+}
+
+#[test]
+fn test_registries_host_different_games_side_by_side() {
+   use crate::{dynamic::{DynError, Registry}, net::WireError, session::SessionError, DynSession};
+
+   let mut registry = Registry::new();
+   registry.register("race", |total : u32| Session::new(Host::new(Race { total, ..Race::default() })).unwrap());
+   registry.register("tally", |() : ()| Session::new(Host::new(Tally::default())).unwrap());
+   assert_eq!(registry.names().collect::<Vec<_>>(), ["race", "tally"]);
+
+   let mut sessions : Vec<Box<dyn DynSession>> =
+      vec![registry.create("race", &7u32.to_bytes()).unwrap(), registry.create("tally", &[]).unwrap()];
+   assert_eq!(sessions[0].state(), GeneratorState::Yielded(Seat(0).to_bytes()));
+   assert_eq!(sessions[1].state(), GeneratorState::Yielded(1u32.to_bytes()));
+
+   assert_eq!(sessions[0].resume_as(Seat(1), &3u32.to_bytes()).unwrap_err(),
+      DynError::Session(SessionError::WrongSeat { seat: Seat(1), expected: Seat(0).into() }));
+   assert_eq!(sessions[0].resume(&[1]), Err(DynError::Wire(WireError)));
+   assert_eq!(sessions[0].resume(&3u32.to_bytes()), Err(DynError::Session(SessionError::SeatRequired)));
+   assert_eq!(sessions[0].resume_as(Seat(0), &3u32.to_bytes()), Ok(GeneratorState::Complete(Seat(0).to_bytes())));
+
+   for _ in 0..3 {
+      sessions[1].resume(&[]).unwrap();
+   }
+   assert_eq!(sessions[1].state(), GeneratorState::Complete(6u32.to_bytes()));
+   assert_eq!(sessions[1].downcast_ref::<Tally>().unwrap().host().borrow_game().handled, 3);
+   assert!(sessions[1].downcast_ref::<Race>().is_none());
+   assert!(matches!(registry.create("chess", &[]), Err(DynError::UnknownGame(name)) if name == "chess"));
+}
+
+#[test]
+fn test_scheduler_routes_inputs_and_retires_finished_sessions() {
+   use crate::{scheduler::{Scheduler, SchedulerError}, session::SessionError};
+
+   let mut scheduler = Scheduler::with_capacity(1000);
+   let ids : Vec<_> = (0..1000).map(|_| scheduler.insert(Session::new(Host::new(Race::default())).unwrap())).collect();
+   assert_eq!(scheduler.len(), 1000);
+
+   // Every session starts by prompting seat 0.
+   let mut sent = 0;
+   scheduler.flush(|_, event| {
+      assert_eq!(event, Seat(0));
+      sent += 1;
+   });
+   assert_eq!(sent, 1000);
+
+   // Finish every other game, with seat 0 adding 3 and seat 1 adding 2 until someone reaches the goal.
+   for &id in ids.iter().step_by(2) {
+      for (seat, amount) in [(Seat(0), 3), (Seat(1), 2), (Seat(0), 3), (Seat(1), 2)] {
+         scheduler.route(id, seat, amount).unwrap();
+      }
+   }
+   assert_eq!(scheduler.drain_outbox(ids[0]).unwrap().collect::<Vec<_>>(), [Seat(1), Seat(0), Seat(1)]);
+   assert_eq!(scheduler.route(ids[1], Seat(1), 3), Err(SchedulerError::Session(SessionError::WrongSeat {
+      seat: Seat(1),
+      expected: Seat(0).into(),
+   })));
+
+   let mut retired = Vec::new();
+   scheduler.retire(|game| retired.push((game.id, game.outcome, game.unsent.len())));
+   assert_eq!(retired.len(), 500);
+   assert_eq!(retired[0], (ids[0], Seat(1), 0));
+   assert!(retired[1..].iter().all(|&(_, winner, unsent)| winner == Seat(1) && unsent == 3));
+   assert_eq!(scheduler.len(), 500);
+
+   // Retired ids stay invalid, even once their slots are reused.
+   let id = scheduler.insert(Session::new(Host::new(Race::default())).unwrap());
+   assert!(scheduler.get(id).is_some() && scheduler.get(ids[0]).is_none());
+   assert_eq!(scheduler.route(ids[0], Seat(0), 1), Err(SchedulerError::UnknownSession(ids[0])));
+   assert!(scheduler.remove(ids[1]).is_some());
+   assert_eq!(scheduler.len(), 500);
+}
+
+#[test]
+fn test_scheduler_collects_events_answered_by_agents() {
+   use crate::scheduler::Scheduler;
+
+   let mut session = Session::new(Host::new(Race::default())).unwrap();
+   session.bind(Seat(1), |_ : &Race, _ : &Seat| 2);
+
+   let mut scheduler = Scheduler::new();
+   let id = scheduler.insert(session);
+   scheduler.route(id, Seat(0), 3).unwrap();
+   scheduler.route(id, Seat(0), 3).unwrap();
+
+   // The bot's turns are sent along with the prompts left for seat 0, right up to the bot's winning move.
+   let mut sent = Vec::new();
+   scheduler.flush(|_, event| sent.push(event));
+   assert_eq!(sent, [Seat(0), Seat(1), Seat(0), Seat(1)]);
+
+   let mut winners = Vec::new();
+   scheduler.retire(|game| winners.push(game.outcome));
+   assert_eq!(winners, [Seat(1)]);
+}
@@ -0,0 +1,337 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::{cmp::Ordering, collections::BTreeSet};
+
+use crate::{rng::Rng, simulate::{play_out, Simulation}, Agent, LegalMoves, Play, Ranked, Seat, Seated, Session};
+
+/// How a [`Tournament`] pairs its entrants up.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+   /// Every entrant plays a match against every other entrant, this many times over.
+   RoundRobin(usize),
+
+   /// Entrants play this many rounds, each time against an entrant with a similar score. Pairs are picked greedily
+   /// from the top of the rankings, with each entrant playing the highest-ranked remaining entrant they haven't played
+   /// yet. This doesn't look ahead, so entrants near the bottom may still be paired for a rematch even when a pairing
+   /// without one exists. With an odd number of entrants, the lowest-ranked entrant without a bye sits out each round
+   /// and scores a win.
+   Swiss(usize),
+}
+
+/// The result of a single game played in a [`Tournament`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+   /// The round the game was played in, starting from `0`.
+   pub round : usize,
+
+   /// The index of the entrant playing as [`Seat(0)`](Seat), then [`Seat(1)`](Seat).
+   pub seats : [usize; 2],
+
+   /// The score of the entrant playing as [`Seat(0)`](Seat): `1.0` for a win, `0.5` for a draw and `0.0` for a loss.
+   /// Games that were abandoned before they were over count as draws.
+   pub score : f64,
+
+   /// `true` if the game was played until it was over.
+   pub is_finished : bool,
+
+   /// The seed that was passed to the tournament's `setup` function, and used to answer any prompts neither entrant
+   /// answered.
+   pub seed : u64,
+
+   /// The seeds that were passed to the agents of the entrants playing as [`Seat(0)`](Seat), then [`Seat(1)`](Seat).
+   pub agent_seeds : [u64; 2],
+}
+
+/// The results of a single entrant in a [`Tournament`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntrantResults {
+   /// The name the entrant was [registered](Tournament::register) with.
+   pub name : String,
+
+   /// The entrant's Elo rating at the end of the tournament.
+   pub rating : f64,
+
+   /// The entrant's total score: one point per win (or bye) and half a point per draw.
+   pub points : f64,
+
+   /// The number of games the entrant won.
+   pub wins : usize,
+
+   /// The number of games the entrant drew, including abandoned games.
+   pub draws : usize,
+
+   /// The number of games the entrant lost.
+   pub losses : usize,
+}
+
+/// Everything recorded while running a [`Tournament`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentResults {
+   /// The results of each entrant, in the order they were registered.
+   pub entrants : Vec<EntrantResults>,
+
+   /// Every game played, in the order it was played.
+   pub games : Vec<GameRecord>,
+}
+
+impl TournamentResults {
+   /// Returns the entrants ordered from highest to lowest rating, breaking ties by points and then by registration
+   /// order.
+   pub fn leaderboard(&self) -> Vec<&EntrantResults> {
+      let mut entrants : Vec<_> = self.entrants.iter().collect();
+      entrants.sort_by(|a, b| b.rating.total_cmp(&a.rating).then(b.points.total_cmp(&a.points)));
+      entrants
+   }
+}
+
+/// Binds a freshly created agent to a seat of a session, given a seed for the agent.
+type BindFn<Game> = Box<dyn Fn(&mut Session<Game>, Seat, u64)>;
+
+/// A registered entrant in a [`Tournament`].
+struct Entrant<Game> where
+   Game : Play + 'static,
+{
+   name : String,
+   bind : BindFn<Game>,
+}
+
+/// Schedules matches between [`Agent`s](Agent) in a two-player game, and rates them using the Elo rating system.
+///
+/// Each game is created by a `setup` function, which is passed a per-game seed and returns a new [`Session`]. The two
+/// entrants in a game are then bound to [`Seat(0)`](Seat) and [`Seat(1)`](Seat), and any prompt neither of them
+/// answers is answered with a random legal input, as in a [`Simulation`]. Each match is made up of several games, with
+/// the entrants swapping seats every game so that neither benefits from always moving first.
+///
+/// ⚠️ **IMPORTANT:** Only two-player games are supported, i.e. games whose [`Outcome`](Play::Outcome) ranks no seats
+/// other than [`Seat(0)`](Seat) and [`Seat(1)`](Seat). See [`run`](Self::run).
+///
+/// Every seed is derived from the tournament's seed, so running the same tournament twice gives the same results.
+pub struct Tournament<Game, Setup> where
+   Game : Play + 'static,
+{
+   setup : Setup,
+   format : Format,
+   entrants : Vec<Entrant<Game>>,
+   games_per_match : usize,
+   seed : u64,
+   k_factor : f64,
+   initial_rating : f64,
+   max_prompts : usize,
+}
+
+impl<Game, Setup> Tournament<Game, Setup> where
+   Game : Play + 'static,
+{
+   /// The default number of games played in each match, one with each entrant in each seat.
+   pub const DEFAULT_GAMES_PER_MATCH : usize = 2;
+
+   /// The default maximum change to a rating after a single game.
+   pub const DEFAULT_K_FACTOR : f64 = 32.0;
+
+   /// The default rating of every entrant before the first game.
+   pub const DEFAULT_RATING : f64 = 1500.0;
+
+   /// Creates a new [`Tournament`] in the given `format`, with each game created by `setup`.
+   pub fn new(format : Format, setup : Setup) -> Self {
+      Self {
+         setup,
+         format,
+         entrants: Vec::new(),
+         games_per_match: Self::DEFAULT_GAMES_PER_MATCH,
+         seed: 0,
+         k_factor: Self::DEFAULT_K_FACTOR,
+         initial_rating: Self::DEFAULT_RATING,
+         max_prompts: Simulation::<Setup>::DEFAULT_MAX_PROMPTS,
+      }
+   }
+
+   /// Registers an entrant called `name`, returning its index. Every game, `agent` is passed a seed of the entrant's
+   /// own and must return a new [`Agent`] to play as the entrant.
+   pub fn register<A>(&mut self, name : impl Into<String>, agent : impl Fn(u64) -> A + 'static) -> usize where
+      Game : Seated,
+      A : Agent<Game> + 'static,
+   {
+      self.entrants.push(Entrant {
+         name: name.into(),
+         bind: Box::new(move |session, seat, seed| session.bind(seat, agent(seed))),
+      });
+      self.entrants.len() - 1
+   }
+
+   /// Sets the number of games played in each match. Defaults to
+   /// [`DEFAULT_GAMES_PER_MATCH`](Self::DEFAULT_GAMES_PER_MATCH).
+   pub fn set_games_per_match(&mut self, games_per_match : usize) {
+      self.games_per_match = games_per_match.max(1);
+   }
+
+   /// Sets the seed from which every game's seed is derived.
+   pub fn set_seed(&mut self, seed : u64) {
+      self.seed = seed;
+   }
+
+   /// Sets the maximum change to a rating after a single game. Defaults to
+   /// [`DEFAULT_K_FACTOR`](Self::DEFAULT_K_FACTOR).
+   pub fn set_k_factor(&mut self, k_factor : f64) {
+      self.k_factor = k_factor;
+   }
+
+   /// Sets the rating of every entrant before the first game. Defaults to [`DEFAULT_RATING`](Self::DEFAULT_RATING).
+   pub fn set_initial_rating(&mut self, rating : f64) {
+      self.initial_rating = rating;
+   }
+
+   /// Sets the number of prompts answered randomly in a single game before giving up on finishing it. Defaults to
+   /// [`Simulation::DEFAULT_MAX_PROMPTS`].
+   pub fn set_max_prompts(&mut self, max_prompts : usize) {
+      self.max_prompts = max_prompts;
+   }
+
+   /// Plays every match in the tournament, returning the results.
+   ///
+   /// # Safety
+   /// This function will panic if a game's [`Outcome`](Play::Outcome) ranks any seat other than [`Seat(0)`](Seat) and
+   /// [`Seat(1)`](Seat), since only two-player games are supported.
+   ///
+   pub fn run(&self) -> TournamentResults where
+      Setup : Fn(u64) -> Session<Game>,
+      Game : LegalMoves + Seated,
+      Game::Input : Clone,
+      Game::Outcome : Ranked,
+   {
+      let mut results = TournamentResults {
+         entrants: self.entrants.iter()
+            .map(|entrant| EntrantResults {
+               name: entrant.name.clone(),
+               rating: self.initial_rating,
+               points: 0.0,
+               wins: 0,
+               draws: 0,
+               losses: 0,
+            })
+            .collect(),
+         games: Vec::new(),
+      };
+
+      let mut seeds = Rng::new(self.seed);
+      match self.format {
+         Format::RoundRobin(rounds) => {
+            for round in 0..rounds {
+               for a in 0..self.entrants.len() {
+                  for b in (a + 1)..self.entrants.len() {
+                     self.play_match(round, [a, b], &mut seeds, &mut results);
+                  }
+               }
+            }
+         },
+         Format::Swiss(rounds) => {
+            let mut played = BTreeSet::new();
+            let mut byes = BTreeSet::new();
+            for round in 0..rounds {
+               let (pairs, bye) = swiss_pairings(&results, &played, &byes);
+               if let Some(bye) = bye {
+                  results.entrants[bye].points += 1.0;
+                  byes.insert(bye);
+               }
+
+               for [a, b] in pairs {
+                  played.insert((a.min(b), a.max(b)));
+                  self.play_match(round, [a, b], &mut seeds, &mut results);
+               }
+            }
+         },
+      }
+
+      results
+   }
+
+   /// Plays a match between the `entrants`, adding the results of each game to `results`.
+   fn play_match(&self, round : usize, entrants : [usize; 2], seeds : &mut Rng, results : &mut TournamentResults) where
+      Setup : Fn(u64) -> Session<Game>,
+      Game : LegalMoves + Seated,
+      Game::Input : Clone,
+      Game::Outcome : Ranked,
+   {
+      for game in 0..self.games_per_match {
+         let seats = if game % 2 == 0 { entrants } else { [entrants[1], entrants[0]] };
+         let seed = seeds.next_u64();
+         let agent_seeds = [seeds.next_u64(), seeds.next_u64()];
+
+         let mut session = (self.setup)(seed);
+         for ((seat, entrant), agent_seed) in [Seat(0), Seat(1)].into_iter().zip(seats).zip(agent_seeds) {
+            (self.entrants[entrant].bind)(&mut session, seat, agent_seed);
+         }
+         play_out(&mut session, &mut Rng::new(seed), self.max_prompts);
+
+         let standings = session.outcome().map(Ranked::standings);
+         let mut players = standings.iter().flat_map(|standings| standings.iter()).map(|placement| placement.player);
+         if let Some(seat) = players.find(|seat| seat.0 > 1) {
+            panic!("tournaments only support two-player games, but {seat:?} was ranked");
+         }
+
+         let ordering = standings.as_ref().and_then(|standings| standings.compare(&Seat(0), &Seat(1)));
+         let score = match ordering {
+            Some(Ordering::Greater) => 1.0,
+            Some(Ordering::Less) => 0.0,
+            _ => 0.5,
+         };
+
+         self.rate(seats, score, results);
+         results.games.push(GameRecord { round, seats, score, is_finished: session.is_complete(), seed, agent_seeds });
+      }
+   }
+
+   /// Updates the ratings and tallies of both `seats` after a game where [`Seat(0)`](Seat) scored `score`.
+   fn rate(&self, seats : [usize; 2], score : f64, results : &mut TournamentResults) {
+      let [a, b] = seats;
+      let expected = 1.0 / (1.0 + 10f64.powf((results.entrants[b].rating - results.entrants[a].rating) / 400.0));
+      let change = self.k_factor * (score - expected);
+
+      for (entrant, score, change) in [(a, score, change), (b, 1.0 - score, -change)] {
+         let entrant = &mut results.entrants[entrant];
+         entrant.rating += change;
+         entrant.points += score;
+         match score.total_cmp(&0.5) {
+            Ordering::Greater => entrant.wins += 1,
+            Ordering::Equal => entrant.draws += 1,
+            Ordering::Less => entrant.losses += 1,
+         }
+      }
+   }
+}
+
+/// Greedily pairs up entrants with similar scores for the next round of a Swiss tournament, pairing each entrant from
+/// the top down with the highest-ranked remaining entrant they haven't played yet (or the highest-ranked remaining
+/// entrant, if they've played them all). Returns the pairs, along with the entrant sitting out this round (if there are
+/// an odd number of entrants).
+fn swiss_pairings(
+   results : &TournamentResults,
+   played : &BTreeSet<(usize, usize)>,
+   byes : &BTreeSet<usize>,
+) -> (Vec<[usize; 2]>, Option<usize>) {
+   let mut order : Vec<usize> = (0..results.entrants.len()).collect();
+   order.sort_by(|&a, &b| {
+      let (a, b) = (&results.entrants[a], &results.entrants[b]);
+      b.points.total_cmp(&a.points).then(b.rating.total_cmp(&a.rating))
+   });
+
+   // The lowest-ranked entrant who hasn't had a bye yet sits out.
+   let bye = if order.len() % 2 == 1 {
+      let index = order.iter().rposition(|entrant| !byes.contains(entrant)).unwrap_or(order.len() - 1);
+      Some(order.remove(index))
+   }
+   else {
+      None
+   };
+
+   let mut pairs = Vec::new();
+   while !order.is_empty() {
+      let a = order.remove(0);
+      let is_new = |b : &usize| !played.contains(&(a.min(*b), a.max(*b)));
+      let index = order.iter().position(is_new).unwrap_or(0);
+      pairs.push([a, order.remove(index)]);
+   }
+
+   (pairs, bye)
+}
@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use std::collections::VecDeque;
+
+use crate::Play;
+
+/// Returned from [`Triggers::push`] when queueing a follow-up event would exceed the maximum trigger depth. This
+/// usually means that a chain of triggered abilities has formed a loop. Every overflow is also recorded by the host,
+/// and can be collected with [`Host::take_trigger_overflows`](crate::Host::take_trigger_overflows).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TriggerOverflow {
+   /// The maximum depth that was exceeded. See [`Host::set_max_trigger_depth`](crate::Host::set_max_trigger_depth).
+   pub max_depth : usize,
+}
+
+/// Queue of follow-up [`Event`s](Play::Event) pushed by [`Play::trigger`] in response to an event emitted by the game.
+/// Queued events are processed and yielded to the UI in first-in, first-out order before the coroutine continues.
+pub struct Triggers<Game> where
+   Game : Play,
+{
+   queue : VecDeque<(usize, Game::Event)>,
+   depth : usize,
+   max_depth : usize,
+   overflows : Vec<TriggerOverflow>,
+}
+
+impl<Game> Triggers<Game> where
+   Game : Play,
+{
+   /// The default value for [`max_depth`](Self::max_depth).
+   pub const DEFAULT_MAX_DEPTH : usize = 16;
+
+   /// Queues a follow-up [`Event`](Play::Event), to be processed after every event already in the queue. If this would
+   /// cause the chain of triggers to grow deeper than [`max_depth`](Self::max_depth), the event is discarded and a
+   /// [`TriggerOverflow`] is returned instead. The overflow is recorded for the host either way, so games are free to
+   /// ignore it.
+   pub fn push(&mut self, event : Game::Event) -> Result<(), TriggerOverflow> {
+      let depth = self.depth + 1;
+      if depth > self.max_depth {
+         let overflow = TriggerOverflow { max_depth: self.max_depth };
+         self.overflows.push(overflow);
+         return Err(overflow);
+      }
+
+      self.queue.push_back((depth, event));
+      Ok(())
+   }
+
+   /// Returns how many triggers deep the event currently being handled is. Events emitted directly by
+   /// [`play`](Play::play) have a depth of `0`, events they trigger have a depth of `1`, and so on.
+   pub fn depth(&self) -> usize {
+      self.depth
+   }
+
+   /// Returns the maximum depth allowed for a chain of triggers.
+   pub fn max_depth(&self) -> usize {
+      self.max_depth
+   }
+
+   /// Returns the number of follow-up events waiting to be processed.
+   pub fn len(&self) -> usize {
+      self.queue.len()
+   }
+
+   /// Returns `true` if there are no follow-up events waiting to be processed.
+   pub fn is_empty(&self) -> bool {
+      self.queue.is_empty()
+   }
+
+   pub(crate) fn set_depth(&mut self, depth : usize) {
+      self.depth = depth;
+   }
+
+   pub(crate) fn set_max_depth(&mut self, max_depth : usize) {
+      self.max_depth = max_depth;
+   }
+
+   pub(crate) fn pop(&mut self) -> Option<(usize, Game::Event)> {
+      self.queue.pop_front()
+   }
+
+   pub(crate) fn take_overflows(&mut self) -> Vec<TriggerOverflow> {
+      std::mem::take(&mut self.overflows)
+   }
+}
+
+impl<Game> Default for Triggers<Game> where
+   Game : Play,
+{
+   fn default() -> Self {
+      Self {
+         queue: VecDeque::new(),
+         depth: 0,
+         max_depth: Self::DEFAULT_MAX_DEPTH,
+         overflows: Vec::new(),
+      }
+   }
+}
@@ -0,0 +1,152 @@
+// SPDX-FileCopyrightText: 2024 Andrew T. Christensen <andrew@andrewtc.com>
+//
+// SPDX-License-Identifier: MIT
+
+use crate::{Play, Seat};
+
+/// Keeps track of whose turn it is in a game with any number of players, e.g. [`Seat`s](Seat). Besides simply moving
+/// around the table, the order can be reversed, players can be skipped or granted extra turns, and players can be
+/// eliminated from the game entirely.
+///
+/// No player has taken a turn until the first call to [`advance`](Self::advance), which starts the first player's turn.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TurnOrder<S = Seat> {
+   players : Vec<S>,
+   index : usize,
+   is_reversed : bool,
+   is_started : bool,
+   is_vacated : bool,
+   skips : usize,
+   extra_turns : usize,
+}
+
+impl<S> TurnOrder<S> {
+   /// Creates a new [`TurnOrder`] visiting `players` in the given order, starting with the first.
+   pub fn new(players : impl IntoIterator<Item = S>) -> Self {
+      Self {
+         players: players.into_iter().collect(),
+         index: 0,
+         is_reversed: false,
+         is_started: false,
+         is_vacated: false,
+         skips: 0,
+         extra_turns: 0,
+      }
+   }
+
+   /// Returns the player whose turn it is (or whose turn is first, if the game has not started yet). Returns [`None`]
+   /// if every player has been eliminated, or if the current player was eliminated and [`advance`](Self::advance) has
+   /// not been called since.
+   pub fn current(&self) -> Option<&S> {
+      if self.is_vacated { None } else { self.players.get(self.index) }
+   }
+
+   /// Iterates over the remaining players, in their original order (regardless of the current direction).
+   pub fn players(&self) -> impl Iterator<Item = &S> {
+      self.players.iter()
+   }
+
+   /// Returns the number of players that have not been eliminated.
+   pub fn len(&self) -> usize {
+      self.players.len()
+   }
+
+   /// Returns `true` if every player has been eliminated.
+   pub fn is_empty(&self) -> bool {
+      self.players.is_empty()
+   }
+
+   /// Returns `true` if turns are currently passed in the opposite of the original order.
+   pub fn is_reversed(&self) -> bool {
+      self.is_reversed
+   }
+
+   /// Reverses the direction in which turns are passed, starting with the next call to [`advance`](Self::advance).
+   pub fn reverse(&mut self) {
+      self.is_reversed = !self.is_reversed;
+   }
+
+   /// Skips over the next `count` players the next time the turn is passed.
+   pub fn skip(&mut self, count : usize) {
+      self.skips += count;
+   }
+
+   /// Grants the current player another turn once this one is over. Extra turns take priority over skips, which only
+   /// apply once the current player has run out of turns.
+   pub fn grant_extra_turn(&mut self) {
+      self.extra_turns += 1;
+   }
+
+   /// Removes `player` from the turn order, returning `true` if it was found. If it was the current player's turn, no
+   /// one's turn it is until the turn is passed to the player that would have been next, and any extra turns granted to
+   /// the eliminated player are forfeited.
+   pub fn eliminate(&mut self, player : &S) -> bool where
+      S : PartialEq,
+   {
+      let Some(index) = self.players.iter().position(|other| other == player) else {
+         return false;
+      };
+
+      self.players.remove(index);
+      if index < self.index {
+         self.index -= 1;
+      }
+      else if index == self.index && !self.is_vacated {
+         self.is_vacated = true;
+         self.extra_turns = 0;
+      }
+
+      true
+   }
+
+   /// Passes the turn to the next player, taking any reversals, skips and extra turns into account, and returns the
+   /// player whose turn it is now. The first call starts the first player's turn. Returns [`None`] if every player has
+   /// been eliminated.
+   pub fn advance(&mut self) -> Option<&S> {
+      if self.players.is_empty() {
+         return None;
+      }
+
+      if !self.is_started {
+         self.is_started = true;
+         self.index %= self.players.len();
+      }
+      else if self.extra_turns > 0 && !self.is_vacated {
+         self.extra_turns -= 1;
+      }
+      else {
+         // When going forwards, an eliminated player's spot has already been taken by the player that follows them.
+         let len = self.players.len();
+         let vacated = usize::from(self.is_vacated && !self.is_reversed);
+         let steps = (1 + std::mem::take(&mut self.skips) - vacated) % len;
+         self.index = if self.is_reversed {
+            (self.index.min(len) + len - steps) % len
+         }
+         else {
+            (self.index + steps) % len
+         };
+      }
+
+      self.is_vacated = false;
+      self.players.get(self.index)
+   }
+
+   /// Returns the player whose turn is already in progress, or passes the turn via [`advance`](Self::advance) if no
+   /// one's turn is in progress (i.e. the first turn has not started, or the current player was eliminated).
+   pub(crate) fn resume(&mut self) -> Option<&S> {
+      if self.is_started && !self.is_vacated { self.current() } else { self.advance() }
+   }
+}
+
+/// Trait for games that keep a [`TurnOrder`] as part of their state, allowing the coroutine to pass the turn with
+/// [`Context::next_turn`](crate::Context::next_turn).
+pub trait TakeTurns : Play {
+   /// Identifies each player in the [`TurnOrder`], e.g. a [`Seat`].
+   type Player : Clone;
+
+   /// Mutably borrows the game's [`TurnOrder`].
+   fn turn_order(&mut self) -> &mut TurnOrder<Self::Player>;
+
+   /// Returns the [`Event`](Play::Event) announcing that it is now `player`'s turn.
+   fn turn_changed(&self, player : &Self::Player) -> Self::Event;
+}